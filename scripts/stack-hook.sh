@@ -1,8 +1,17 @@
 # stack-game shell hook
-# Sends START/END events to /tmp/stack-game.sock for each command.
+# Sends START/END events to $STACK_SOCK (default /tmp/stack-game.sock) for
+# each command; `waitris --project <name>` sets it for that project's game.
 
-STACK_SOCK="/tmp/stack-game.sock"
+STACK_SOCK="${STACK_SOCK:-/tmp/stack-game.sock}"
 STACK_CMD_ID=0
+# Set STACK_STDERR_FILE to a file your shell's stderr is tee'd into to report
+# how many stderr lines each command printed.
+
+stack_stderr_count() {
+  if [ -n "$STACK_STDERR_FILE" ] && [ -f "$STACK_STDERR_FILE" ]; then
+    wc -l < "$STACK_STDERR_FILE" | tr -d ' '
+  fi
+}
 
 stack_send() {
   local line="$1"
@@ -16,21 +25,45 @@ stack_send() {
   fi
 }
 
+# Milliseconds since the epoch, when the shell can tell (bash 5+, zsh).
+stack_now_ms() {
+  if [ -n "$EPOCHREALTIME" ]; then
+    local t="${EPOCHREALTIME/[.,]/}"
+    echo $((t / 1000))
+  fi
+}
+
 stack_preexec() {
   STACK_CMD_ID=$((STACK_CMD_ID + 1))
   STACK_LAST_CMD="$1"
-  stack_send "START ${STACK_CMD_ID} ${STACK_LAST_CMD}"
+  STACK_STDERR_MARK=$(stack_stderr_count)
+  STACK_START_MS=$(stack_now_ms)
+  # Length-prefixed so quotes and newlines in the command arrive intact.
+  local len
+  len=$(printf "%s" "$STACK_LAST_CMD" | wc -c | tr -d ' ')
+  stack_send "STARTN ${STACK_CMD_ID} ${len}
+${STACK_LAST_CMD}"
 }
 
 stack_precmd() {
   local code=$?
   if [ -n "$STACK_CMD_ID" ]; then
-    stack_send "END ${STACK_CMD_ID} ${code}"
+    local now extra=""
+    now=$(stack_stderr_count)
+    if [ -n "$now" ] && [ -n "$STACK_STDERR_MARK" ]; then
+      extra=" stderr=$((now - STACK_STDERR_MARK))"
+    fi
+    now=$(stack_now_ms)
+    if [ -n "$now" ] && [ -n "$STACK_START_MS" ]; then
+      extra="${extra} ms=$((now - STACK_START_MS))"
+    fi
+    stack_send "END ${STACK_CMD_ID} ${code}${extra}"
   fi
 }
 
 # Zsh integration
 if [ -n "$ZSH_VERSION" ]; then
+  zmodload zsh/datetime 2>/dev/null
   autoload -Uz add-zsh-hook
   add-zsh-hook preexec stack_preexec
   add-zsh-hook precmd stack_precmd
@@ -0,0 +1,161 @@
+use std::env;
+use std::error::Error;
+use std::io::{stdin, stdout, BufRead, IsTerminal, Write};
+use std::os::unix::net::UnixListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use log::debug;
+
+use crate::app::gravity::GravityClock;
+use crate::app::runtime::{open_recorder, step};
+use crate::config::TerminalFallback;
+use crate::game::{Action, Input};
+use crate::io::{answer_query, spawn_socket_listener, SocketMessage};
+use crate::replay::export::export_size;
+use crate::ui::{buffer_lines, render_to_buffer, View};
+use crate::{Config, Game, GRAVITY_MS};
+
+const LINE_REDRAW: Duration = Duration::from_secs(1);
+const LINE_PASS: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    pub raw_mode: bool,
+    pub alt_screen: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frontend {
+    Full,
+    Lines,
+}
+
+// Raw mode is tried for real and undone at once; the alternate screen can't
+// be asked about, so a dumb or missing TERM is taken to mean it's absent.
+pub fn probe() -> Capabilities {
+    let tty = stdin().is_terminal() && stdout().is_terminal();
+    let raw_mode = tty && enable_raw_mode().and_then(|()| disable_raw_mode()).is_ok();
+    let term = env::var("TERM").unwrap_or_default();
+    let alt_screen = tty && !term.is_empty() && term != "dumb";
+    Capabilities { raw_mode, alt_screen }
+}
+
+pub fn choose_frontend(caps: Capabilities, fallback: TerminalFallback) -> Result<Frontend, String> {
+    let missing: Vec<&str> = [(caps.raw_mode, "raw mode"), (caps.alt_screen, "an alternate screen")]
+        .into_iter()
+        .filter(|&(has, _)| !has)
+        .map(|(_, what)| what)
+        .collect();
+    if missing.is_empty() {
+        return Ok(Frontend::Full);
+    }
+    match fallback {
+        TerminalFallback::Lines => Ok(Frontend::Lines),
+        TerminalFallback::Error => Err(format!(
+            "this terminal has no {}, which the waitris UI needs; run it in a real terminal \
+             or set STACK_TERMINAL_FALLBACK=lines for a plain-text view",
+            missing.join(" or ")
+        )),
+    }
+}
+
+// The reduced-fidelity game: the board printed as plain text whenever it
+// changes (at most once per `LINE_REDRAW`), moves read a line at a time.
+pub fn run_lines(config: Config, listener: UnixListener) -> Result<(), Box<dyn Error>> {
+    let (width, height) = export_size(&config);
+    let mut game = Game::with_config(config);
+    let view = View::default();
+    let mut recorder = open_recorder(&game)?;
+    let started = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    spawn_socket_listener(listener, tx);
+    let (line_tx, line_rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in stdin().lock().lines().map_while(Result::ok) {
+            if line_tx.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    let mut out = stdout();
+    let mut last_frame = Instant::now();
+    let mut gravity_clock = GravityClock::default();
+    let gravity = Duration::from_millis(GRAVITY_MS);
+    let mut next_redraw = Instant::now();
+    let mut shown = Vec::new();
+
+    loop {
+        for msg in rx.try_iter() {
+            match msg {
+                SocketMessage::Event(ev) => step(&mut game, &mut recorder, started, Input::Command(ev)),
+                SocketMessage::Query(query, reply) => {
+                    let _ = reply.send(answer_query(&query, &game));
+                }
+                SocketMessage::Debug(input) if game.config.debug_commands => {
+                    step(&mut game, &mut recorder, started, input);
+                }
+                SocketMessage::Debug(input) => debug!("debug commands are off: ignoring {input:?}"),
+            }
+        }
+        for line in line_rx.try_iter() {
+            let word = line.trim();
+            if word == "q" || word == "quit" {
+                return Ok(());
+            }
+            match Action::from_name(word) {
+                Some(action) => step(&mut game, &mut recorder, started, Input::Action(action)),
+                None if word.is_empty() => {}
+                None => writeln!(out, "unknown move `{word}`")?,
+            }
+        }
+
+        let now = Instant::now();
+        let dt = Duration::from_micros((now - last_frame).as_micros() as u64);
+        last_frame = now;
+        step(&mut game, &mut recorder, started, Input::Frame(dt));
+        for _ in 0..gravity_clock.advance(dt, gravity) {
+            step(&mut game, &mut recorder, started, Input::Gravity);
+        }
+
+        if now >= next_redraw {
+            let lines = buffer_lines(&render_to_buffer(&game, &view, width, height), false);
+            if lines != shown {
+                writeln!(out, "{}\n", lines.join("\n"))?;
+                out.flush()?;
+                shown = lines;
+            }
+            next_redraw = now + LINE_REDRAW;
+        }
+        thread::sleep(LINE_PASS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_features_select_the_fallback() {
+        let full = Capabilities {
+            raw_mode: true,
+            alt_screen: true,
+        };
+        assert_eq!(choose_frontend(full, TerminalFallback::Error), Ok(Frontend::Full));
+        for caps in [
+            Capabilities { raw_mode: false, ..full },
+            Capabilities { alt_screen: false, ..full },
+        ] {
+            assert_eq!(choose_frontend(caps, TerminalFallback::Lines), Ok(Frontend::Lines));
+            assert!(choose_frontend(caps, TerminalFallback::Error).is_err());
+        }
+        let bare = Capabilities {
+            raw_mode: false,
+            alt_screen: false,
+        };
+        let err = choose_frontend(bare, TerminalFallback::Error).unwrap_err();
+        assert!(err.contains("no raw mode or an alternate screen"), "{err}");
+    }
+}
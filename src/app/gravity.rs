@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+// Fraction of the gap to the current backlog closed per loop pass (~20/s).
+const BACKLOG_SMOOTHING: f32 = 0.05;
+// Each pending piece shortens the interval by this fraction of the base.
+const PRESSURE_PER_PIECE: f32 = 0.1;
+const MIN_GRAVITY: Duration = Duration::from_millis(120);
+// Most gravity steps one loop pass catches up on, so a stalled loop (a
+// suspended terminal, a slow draw) doesn't slam the piece down on resume.
+const MAX_CATCH_UP: u32 = 3;
+
+// Exponential moving average of the backlog so short bursts don't jitter gravity.
+#[derive(Default)]
+pub struct BacklogSmoother {
+    level: f32,
+}
+
+impl BacklogSmoother {
+    pub fn update(&mut self, backlog: usize) -> f32 {
+        self.level += (backlog as f32 - self.level) * BACKLOG_SMOOTHING;
+        self.level
+    }
+}
+
+// Gravity runs on accumulated time rather than on loop passes, so it keeps its
+// own schedule however fast the screen redraws.
+#[derive(Default)]
+pub struct GravityClock {
+    pending: Duration,
+}
+
+impl GravityClock {
+    pub fn advance(&mut self, dt: Duration, interval: Duration) -> u32 {
+        self.pending += dt;
+        let mut due = 0;
+        while self.pending >= interval && due < MAX_CATCH_UP {
+            self.pending -= interval;
+            due += 1;
+        }
+        if self.pending >= interval {
+            self.pending = Duration::ZERO;
+        }
+        due
+    }
+}
+
+pub fn pressure_interval(base: Duration, backlog: f32) -> Duration {
+    base.mul_f32(1.0 / (1.0 + backlog.max(0.0) * PRESSURE_PER_PIECE))
+        .max(MIN_GRAVITY.min(base))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_backlog_gives_faster_gravity() {
+        let base = Duration::from_millis(450);
+        assert!(base - pressure_interval(base, 0.0) < Duration::from_micros(1));
+        let intervals: Vec<Duration> = [1.0, 4.0, 10.0].iter().map(|&b| pressure_interval(base, b)).collect();
+        assert!(intervals[0] < base);
+        assert!(intervals.windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(pressure_interval(base, 1000.0), MIN_GRAVITY);
+        // A base already faster than the floor is never slowed down.
+        let quick = Duration::from_millis(50);
+        assert_eq!(pressure_interval(quick, 1000.0), quick);
+    }
+
+    #[test]
+    fn gravity_clock_fires_on_accumulated_time() {
+        let interval = Duration::from_millis(100);
+        let mut clock = GravityClock::default();
+        // 60 fps for two seconds: twenty steps, however the frames fall.
+        let ticks: u32 = (0..120).map(|_| clock.advance(Duration::from_micros(16_667), interval)).sum();
+        assert_eq!(ticks, 20);
+        // A long stall catches up on at most `MAX_CATCH_UP` and drops the rest.
+        assert_eq!(clock.advance(Duration::from_secs(5), interval), MAX_CATCH_UP);
+        assert_eq!(clock.advance(Duration::from_millis(50), interval), 0);
+        assert_eq!(clock.advance(Duration::from_millis(50), interval), 1);
+    }
+}
@@ -1,3 +1,6 @@
+mod fallback;
+mod gravity;
+mod repeat;
 mod runtime;
 
 pub use runtime::run;
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+// Terminals report no key releases, only presses, auto-repeated every few
+// tens of ms while a key is held. A key counts as held until this long passes
+// without another press.
+const HOLD_GAP: Duration = Duration::from_millis(120);
+
+// Auto-repeat for a held key at our own rate rather than the terminal's. A tap
+// acts once; once the terminal starts repeating, the action fires every `rate`.
+pub struct KeyRepeat {
+    rate: Duration,
+    held: bool,
+    // A second press arrived within the gap, so this is a hold, not a tap.
+    repeating: bool,
+    since_press: Duration,
+    since_fire: Duration,
+}
+
+impl KeyRepeat {
+    pub fn new(rate: Duration) -> Self {
+        Self {
+            rate,
+            held: false,
+            repeating: false,
+            since_press: Duration::ZERO,
+            since_fire: Duration::ZERO,
+        }
+    }
+
+    // Note a press of the key. True when it should act right away: only the
+    // first press of a hold does, repeats after it are paced by `advance`.
+    pub fn press(&mut self) -> bool {
+        self.since_press = Duration::ZERO;
+        if self.held {
+            self.repeating = true;
+            return false;
+        }
+        self.held = true;
+        self.repeating = false;
+        self.since_fire = Duration::ZERO;
+        true
+    }
+
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        if !self.held {
+            return 0;
+        }
+        self.since_press += dt;
+        if self.since_press > HOLD_GAP {
+            self.held = false;
+            return 0;
+        }
+        if !self.repeating {
+            return 0;
+        }
+        self.since_fire += dt;
+        let mut due = 0;
+        while self.since_fire >= self.rate {
+            self.since_fire -= self.rate;
+            due += 1;
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn held_key_repeats_at_its_own_rate() {
+        let mut key = KeyRepeat::new(ms(25));
+        // A tap acts once and then lapses.
+        assert!(key.press());
+        assert_eq!(key.advance(ms(100)), 0);
+        assert_eq!(key.advance(ms(30)), 0);
+        // A hold: the terminal's repeats don't act, our rate does.
+        assert!(key.press());
+        assert_eq!(key.advance(ms(30)), 0);
+        assert!(!key.press());
+        assert_eq!(key.advance(ms(60)), 2);
+        assert!(!key.press());
+        assert_eq!(key.advance(ms(15)), 1);
+        // Released: nothing more fires.
+        assert_eq!(key.advance(ms(200)), 0);
+        assert_eq!(key.advance(ms(50)), 0);
+    }
+}
@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::io::{stdout, Stdout};
+use std::os::unix::net::UnixListener;
 use std::process::Command;
 use std::sync::mpsc;
 use std::time::{Duration, Instant};
@@ -7,49 +8,112 @@ use std::time::{Duration, Instant};
 use crossterm::event::{self, Event, KeyCode};
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use log::debug;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 
-use crate::io::spawn_socket_listener;
-use crate::ui::draw_game;
-use crate::Game;
+use crate::app::fallback::{choose_frontend, probe, run_lines, Frontend};
+use crate::app::gravity::{pressure_interval, BacklogSmoother, GravityClock};
+use crate::app::repeat::KeyRepeat;
+use crate::game::{Action, Input};
+use crate::io::{answer_query, bind_socket, spawn_socket_listener, SocketMessage};
+use crate::replay::Recorder;
+use crate::ui::{draw_game, View};
+use crate::{Config, Game, GRAVITY_MS};
 
 type Term = Terminal<CrosstermBackend<Stdout>>;
 
-pub fn run() -> Result<(), Box<dyn Error>> {
+pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let caps = probe();
+    let frontend = choose_frontend(caps, config.terminal_fallback)?;
+    debug!("terminal capabilities {caps:?}: {frontend:?} frontend");
+    if frontend == Frontend::Lines {
+        let listener = bind_socket(&config.socket_path)?;
+        return run_lines(config, listener);
+    }
+    // Bind before taking over the terminal so a clash is reported in plain text.
+    let listener = bind_socket(&config.socket_path)?;
     let mut tui = TuiGuard::new()?;
-    let result = run_loop(tui.terminal_mut());
+    let result = run_loop(tui.terminal_mut(), config, listener);
     cleanup_tmux_on_exit();
     result
 }
 
-fn run_loop(terminal: &mut Term) -> Result<(), Box<dyn Error>> {
-    let mut game = Game::new();
+fn run_loop(terminal: &mut Term, config: Config, listener: UnixListener) -> Result<(), Box<dyn Error>> {
+    let mut game = Game::with_config(config);
+    let mut view = View::default();
+    let mut recorder = open_recorder(&game)?;
+    let started = Instant::now();
     let (tx, rx) = mpsc::channel();
-    spawn_socket_listener(tx);
-    let mut last_tick = Instant::now();
+    spawn_socket_listener(listener, tx);
+    let mut last_frame = Instant::now();
+    let mut gravity_clock = GravityClock::default();
+    // Redraw at a steady rate of its own, whatever the gravity interval.
+    let render_interval = Duration::from_secs(1) / game.config.render_fps;
+    let mut next_render = Instant::now();
+    let mut backlog = BacklogSmoother::default();
+    let base_gravity = Duration::from_millis(GRAVITY_MS);
+    // Without a configured rate, soft drop moves once per terminal key repeat.
+    let mut soft_drop_repeat = game.config.soft_drop_rate.map(KeyRepeat::new);
 
     loop {
-        for ev in rx.try_iter() {
-            game.handle_command_event(ev);
+        for msg in rx.try_iter() {
+            match msg {
+                SocketMessage::Event(ev) => {
+                    step(&mut game, &mut recorder, started, Input::Command(ev));
+                }
+                SocketMessage::Query(query, reply) => {
+                    let _ = reply.send(answer_query(&query, &game));
+                }
+                SocketMessage::Debug(input) if game.config.debug_commands => {
+                    step(&mut game, &mut recorder, started, input);
+                }
+                SocketMessage::Debug(input) => debug!("debug commands are off: ignoring {input:?}"),
+            }
         }
 
-        terminal.draw(|frame| draw_game(frame, &game))?;
+        if Instant::now() >= next_render {
+            terminal.draw(|frame| draw_game(frame, &game, &view))?;
+            next_render = Instant::now() + render_interval;
+        }
 
-        game.process_effects();
+        // Whole microseconds, so the recorded step replays exactly.
+        let now = Instant::now();
+        let dt = Duration::from_micros((now - last_frame).as_micros() as u64);
+        last_frame = now;
+        step(&mut game, &mut recorder, started, Input::Frame(dt));
+        if let Some(repeat) = &mut soft_drop_repeat {
+            for _ in 0..repeat.advance(dt) {
+                step(&mut game, &mut recorder, started, Input::Action(Action::SoftDrop));
+            }
+        }
 
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                if matches!(key.code, KeyCode::Char('q')) {
-                    break;
+        let until_render = next_render.saturating_duration_since(Instant::now());
+        if event::poll(until_render)?
+            && let Event::Key(key) = event::read()?
+        {
+            if matches!(key.code, KeyCode::Char('q')) {
+                break;
+            }
+            if let Some(action) = key_action(key.code) {
+                let repeated = action == Action::SoftDrop
+                    && soft_drop_repeat.as_mut().is_some_and(|repeat| !repeat.press());
+                if !repeated {
+                    step(&mut game, &mut recorder, started, Input::Action(action));
                 }
-                handle_input(key.code, &mut game);
+            } else {
+                handle_view_key(key.code, &mut view);
             }
         }
 
-        if last_tick.elapsed() >= Duration::from_millis(450) {
-            game.tick_gravity();
-            last_tick = Instant::now();
+        let smoothed = backlog.update(game.pending_pieces());
+        let gravity = if game.config.pressure_gravity {
+            pressure_interval(base_gravity, smoothed)
+        } else {
+            base_gravity
+        };
+        for _ in 0..gravity_clock.advance(dt, gravity) {
+            step(&mut game, &mut recorder, started, Input::Gravity);
         }
     }
     Ok(())
@@ -62,12 +126,21 @@ struct TuiGuard {
 impl TuiGuard {
     fn new() -> Result<Self, Box<dyn Error>> {
         enable_raw_mode()?;
+        // Don't leave the terminal in raw mode if the rest of setup fails.
+        let terminal = Self::enter().inspect_err(|_| {
+            let _ = disable_raw_mode();
+            let _ = execute!(stdout(), LeaveAlternateScreen);
+        })?;
+        Ok(Self { terminal })
+    }
+
+    fn enter() -> Result<Term, Box<dyn Error>> {
         let mut stdout = stdout();
         execute!(stdout, EnterAlternateScreen)?;
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
         terminal.hide_cursor()?;
-        Ok(Self { terminal })
+        Ok(terminal)
     }
 
     fn terminal_mut(&mut self) -> &mut Term {
@@ -83,23 +156,39 @@ impl Drop for TuiGuard {
     }
 }
 
-fn handle_input(code: KeyCode, game: &mut Game) {
+pub(super) fn open_recorder(game: &Game) -> Result<Option<Recorder>, String> {
+    match &game.config.record {
+        Some(path) => Recorder::create(path, game.seed, &game.config.settings)
+            .map(Some)
+            .map_err(|e| format!("cannot record to {}: {e}", path.display())),
+        None => Ok(None),
+    }
+}
+
+pub(super) fn step(game: &mut Game, recorder: &mut Option<Recorder>, started: Instant, input: Input) {
+    if let Some(rec) = recorder {
+        rec.record(started.elapsed(), &input);
+    }
+    game.apply_input(input);
+}
+
+fn key_action(code: KeyCode) -> Option<Action> {
+    Some(match code {
+        KeyCode::Left => Action::Left,
+        KeyCode::Right => Action::Right,
+        KeyCode::Down => Action::SoftDrop,
+        KeyCode::Up => Action::Rotate,
+        KeyCode::Char(' ') => Action::HardDrop,
+        KeyCode::Char('r') => Action::Restart,
+        KeyCode::Tab => Action::Priority,
+        _ => return None,
+    })
+}
+
+fn handle_view_key(code: KeyCode, view: &mut View) {
     match code {
-        KeyCode::Left => {
-            let _ = game.move_current(-1, 0);
-        }
-        KeyCode::Right => {
-            let _ = game.move_current(1, 0);
-        }
-        KeyCode::Down => {
-            let _ = game.move_current(0, 1);
-        }
-        KeyCode::Up => {
-            let _ = game.rotate_current();
-        }
-        KeyCode::Char(' ') => {
-            game.hard_drop();
-        }
+        KeyCode::Char('h') => view.heatmap = !view.heatmap,
+        KeyCode::Char('o') => view.origins = !view.origins,
         _ => {}
     }
 }
@@ -114,17 +203,17 @@ fn cleanup_tmux_on_exit() {
     if kill_session {
         if let Ok(session) = tmux_current_session() {
             let _ = Command::new("tmux")
-                .args(&["kill-session", "-t", &session])
+                .args(["kill-session", "-t", &session])
                 .status();
         }
     } else {
-        let _ = Command::new("tmux").args(&["kill-pane"]).status();
+        let _ = Command::new("tmux").args(["kill-pane"]).status();
     }
 }
 
 fn tmux_current_session() -> Result<String, Box<dyn Error>> {
     let out = Command::new("tmux")
-        .args(&["display-message", "-p", "#S"])
+        .args(["display-message", "-p", "#S"])
         .output()?;
     if out.status.success() {
         let name = String::from_utf8_lossy(&out.stdout).trim().to_string();
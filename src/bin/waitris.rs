@@ -1,25 +1,46 @@
 use std::env;
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
 use std::path::{Path, PathBuf};
 use std::process::{Command, ExitCode};
+use std::time::Duration;
+
+#[path = "../project.rs"]
+mod project;
 
 const HOOK_SOURCE: &str = include_str!("../../scripts/stack-hook.sh");
+const STATUS_TIMEOUT: Duration = Duration::from_millis(500);
 
 fn main() -> ExitCode {
-    let mut args = env::args().skip(1);
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `--project <name>` may come anywhere; everything else is the subcommand.
+    let project = project::project_arg(&args).map(str::to_string);
+    if let Some(pos) = args.iter().position(|arg| arg == "--project") {
+        args.drain(pos..(pos + 2).min(args.len()));
+    }
+    let project = project.as_deref();
+    let mut args = args.into_iter();
     match args.next().as_deref() {
-        None => run_launcher(),
-        Some("quit") => quit_session(),
+        None => run_launcher(project),
+        Some("quit") => quit_session(project),
+        Some("status") => show_status(project),
+        Some("attach") => attach_session(project),
         Some("install-hook") => install_hook(),
         Some("uninstall-hook") => uninstall_hook(),
+        Some("replay") => run_game_tool("replay", args.collect()),
+        Some("validate") => run_game_tool("validate", args.collect()),
+        Some("diff") => run_game_tool("diff", args.collect()),
         _ => {
-            eprintln!("usage: waitris [quit|install-hook|uninstall-hook]");
+            eprintln!(
+                "usage: waitris [--project <name>] [quit|status|attach|install-hook|uninstall-hook|replay|validate|diff]"
+            );
             ExitCode::from(2)
         }
     }
 }
 
-fn run_launcher() -> ExitCode {
+fn run_launcher(project: Option<&str>) -> ExitCode {
     let pane_w = env::var("STACK_PANE_W")
         .ok()
         .and_then(|s| s.parse::<u16>().ok())
@@ -39,9 +60,9 @@ fn run_launcher() -> ExitCode {
     };
 
     let result = if env::var("TMUX").is_ok() {
-        run_inside_tmux(pane_w, &game_cmd)
+        run_inside_tmux(pane_w, &game_cmd, project)
     } else {
-        run_new_tmux_session(pane_w, &game_cmd)
+        run_new_tmux_session(pane_w, &game_cmd, project)
     };
 
     if let Err(err) = result {
@@ -52,6 +73,25 @@ fn run_launcher() -> ExitCode {
     }
 }
 
+// Offline tools (replays etc.) live in the game binary; run it directly.
+fn run_game_tool(tool: &str, args: Vec<String>) -> ExitCode {
+    let game_cmd = match game_binary_path() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("cannot find stack-game binary: {e}");
+            return ExitCode::from(1);
+        }
+    };
+    match Command::new(&game_cmd).arg(tool).args(&args).status() {
+        Ok(s) if s.success() => ExitCode::SUCCESS,
+        Ok(s) => ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(e) => {
+            eprintln!("waitris {tool}: cannot run {game_cmd}: {e}");
+            ExitCode::from(1)
+        }
+    }
+}
+
 fn tmux_available() -> bool {
     Command::new("tmux")
         .arg("-V")
@@ -60,19 +100,20 @@ fn tmux_available() -> bool {
         .unwrap_or(false)
 }
 
-fn run_inside_tmux(pane_w: u16, game_cmd: &str) -> Result<(), String> {
+// The game command line, pointed at the project's socket when there is one.
+fn game_args(game_cmd: &str, project: Option<&str>) -> Vec<String> {
+    let mut args = vec![game_cmd.to_string()];
+    if let Some(name) = project {
+        args.extend(["--project".to_string(), name.to_string()]);
+    }
+    args
+}
+
+fn run_inside_tmux(pane_w: u16, game_cmd: &str, project: Option<&str>) -> Result<(), String> {
     let pct = percent_for_width(pane_w);
     let status = Command::new("tmux")
-        .args(&[
-            "split-window",
-            "-h",
-            "-p",
-            &pct,
-            "env",
-            "STACK_MANAGED=1",
-            "STACK_KILL_SESSION=0",
-            game_cmd,
-        ])
+        .args(["split-window", "-h", "-p", &pct, "env", "STACK_MANAGED=1", "STACK_KILL_SESSION=0"])
+        .args(game_args(game_cmd, project))
         .status()
         .map_err(|e| format!("tmux split failed: {e}"))?;
     if !status.success() {
@@ -80,17 +121,29 @@ fn run_inside_tmux(pane_w: u16, game_cmd: &str) -> Result<(), String> {
     }
     if let Ok(session) = current_session_name() {
         apply_session_options(&session);
+        if let Some(name) = project {
+            // New panes pick this up; the shell already running needs telling.
+            let sock = project::socket_path(Some(name));
+            let _ = Command::new("tmux")
+                .args(["set-environment", "-t", &session, "STACK_SOCK", &sock])
+                .status();
+            println!("waitris: run `export STACK_SOCK={sock}` in this shell so its hook reaches the game");
+        }
     }
-    let _ = Command::new("tmux").args(&["select-pane", "-L"]).status();
+    let _ = Command::new("tmux").args(["select-pane", "-L"]).status();
     Ok(())
 }
 
-fn run_new_tmux_session(pane_w: u16, game_cmd: &str) -> Result<(), String> {
+fn run_new_tmux_session(pane_w: u16, game_cmd: &str, project: Option<&str>) -> Result<(), String> {
     let pct = percent_for_width(pane_w);
     let shell = env::var("SHELL").unwrap_or_else(|_| "bash".to_string());
-    let session = format!("waitris-{}", std::process::id());
+    let session = match project {
+        Some(name) => session_name(name),
+        None => format!("waitris-{}", std::process::id()),
+    };
+    let sock = format!("STACK_SOCK={}", project::socket_path(project));
     let status = Command::new("tmux")
-        .args(&["-f", "/dev/null", "new-session", "-d", "-s", &session, &shell])
+        .args(["-f", "/dev/null", "new-session", "-d", "-s", &session, "-e", &sock, &shell])
         .status()
         .map_err(|e| format!("tmux new-session failed: {e}"))?;
     if !status.success() {
@@ -98,57 +151,57 @@ fn run_new_tmux_session(pane_w: u16, game_cmd: &str) -> Result<(), String> {
     }
     apply_session_options(&session);
     let status = Command::new("tmux")
-        .args(&[
-            "split-window",
-            "-h",
-            "-p",
-            &pct,
-            "env",
-            "STACK_MANAGED=1",
-            "STACK_KILL_SESSION=1",
-            game_cmd,
-        ])
+        .args(["split-window", "-h", "-p", &pct, "env", "STACK_MANAGED=1", "STACK_KILL_SESSION=1"])
+        .args(game_args(game_cmd, project))
         .status()
         .map_err(|e| format!("tmux split failed: {e}"))?;
     if !status.success() {
         return Err(format!("tmux split failed with status {}", status));
     }
-    let _ = Command::new("tmux").args(&["select-pane", "-L"]).status();
+    let _ = Command::new("tmux").args(["select-pane", "-L"]).status();
     let _ = Command::new("tmux")
-        .args(&["attach-session", "-t", &session])
+        .args(["attach-session", "-t", &session])
         .status();
     Ok(())
 }
 
 fn apply_session_options(session: &str) {
     let _ = Command::new("tmux")
-        .args(&["set", "-t", session, "status", "off"])
+        .args(["set", "-t", session, "status", "off"])
         .status();
     let _ = Command::new("tmux")
-        .args(&["set", "-t", session, "pane-border-status", "off"])
+        .args(["set", "-t", session, "pane-border-status", "off"])
         .status();
     let _ = Command::new("tmux")
-        .args(&["set", "-t", session, "display-panes-time", "1"])
+        .args(["set", "-t", session, "display-panes-time", "1"])
         .status();
     let _ = Command::new("tmux")
-        .args(&["set-hook", "-t", session, "pane-exited", "kill-session"])
+        .args(["set-hook", "-t", session, "pane-exited", "kill-session"])
         .status();
     let _ = Command::new("tmux")
-        .args(&["bind-key", "-n", "C-Space", "select-pane", "-t", ":.+"])
+        .args(["bind-key", "-n", "C-Space", "select-pane", "-t", ":.+"])
         .status();
 }
 
-fn quit_session() -> ExitCode {
-    if env::var("TMUX").is_err() {
-        eprintln!("waitris quit must be run inside tmux");
-        return ExitCode::from(1);
-    }
-    let session = match current_session_name() {
-        Ok(s) if !s.is_empty() => s,
-        _ => return ExitCode::from(1),
+fn session_name(project: &str) -> String {
+    format!("waitris-{}", project::sanitize(project))
+}
+
+// With a project, its session wherever you run this; otherwise the current one.
+fn quit_session(project: Option<&str>) -> ExitCode {
+    let session = match project {
+        Some(name) => session_name(name),
+        None if env::var("TMUX").is_err() => {
+            eprintln!("waitris quit must be run inside tmux (or given --project)");
+            return ExitCode::from(1);
+        }
+        None => match current_session_name() {
+            Ok(s) if !s.is_empty() => s,
+            _ => return ExitCode::from(1),
+        },
     };
     let status = Command::new("tmux")
-        .args(&["kill-session", "-t", &session])
+        .args(["kill-session", "-t", &session])
         .status();
     match status {
         Ok(s) if s.success() => ExitCode::SUCCESS,
@@ -157,6 +210,44 @@ fn quit_session() -> ExitCode {
     }
 }
 
+fn show_status(project: Option<&str>) -> ExitCode {
+    let sock = project::socket_path(project);
+    let reply = UnixStream::connect(&sock).and_then(|mut stream| {
+        stream.set_read_timeout(Some(STATUS_TIMEOUT))?;
+        writeln!(stream, "SCORE")?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(line)
+    });
+    match reply {
+        Ok(line) if !line.trim().is_empty() => {
+            println!("running on {sock}: {}", line.trim());
+            ExitCode::SUCCESS
+        }
+        _ => {
+            println!("no game on {sock}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+// Bring up the project's session: switch to it from inside tmux, attach otherwise.
+fn attach_session(project: Option<&str>) -> ExitCode {
+    let Some(name) = project else {
+        eprintln!("usage: waitris attach --project <name>");
+        return ExitCode::from(2);
+    };
+    let session = session_name(name);
+    let verb = if env::var("TMUX").is_ok() { "switch-client" } else { "attach-session" };
+    match Command::new("tmux").args([verb, "-t", &session]).status() {
+        Ok(s) if s.success() => ExitCode::SUCCESS,
+        _ => {
+            eprintln!("no waitris session for project {name} ({session})");
+            ExitCode::from(1)
+        }
+    }
+}
+
 fn install_hook() -> ExitCode {
     let hook_path = match hook_path() {
         Ok(p) => p,
@@ -247,7 +338,7 @@ fn rc_path(rc_name: &str) -> Result<PathBuf, String> {
 
 fn current_session_name() -> Result<String, String> {
     let out = Command::new("tmux")
-        .args(&["display-message", "-p", "#S"])
+        .args(["display-message", "-p", "#S"])
         .output()
         .map_err(|e| e.to_string())?;
     if !out.status.success() {
@@ -1,5 +1,7 @@
 mod chunk;
+mod pattern;
 mod tokenize;
 
 pub use chunk::{chunk_to_payload, command_to_chunks};
+pub use pattern::Pattern;
 pub use tokenize::tokenize_command;
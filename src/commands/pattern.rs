@@ -0,0 +1,477 @@
+// Small regex subset used to filter commands: literals, `.`, `^`/`$`, classes
+// (`[a-z]`, `[^...]`, `\d \w \s` and their negations), groups with `|`, and
+// the `* + ? {n,m}` quantifiers. A pattern is compiled once into a small
+// NFA program and matched by stepping every live thread along the text
+// together, so matching is linear in the text and never recurses over it.
+
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    prog: Vec<Inst>,
+}
+
+// Counted repetition is expanded while compiling; this bounds the program.
+const MAX_PROGRAM: usize = 10_000;
+
+#[derive(Clone, Debug)]
+enum Node {
+    Literal(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    // Continue at both targets.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Clone, Debug)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Clone, Debug)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, ch: char) -> bool {
+        match *self {
+            ClassItem::Range(lo, hi) => lo <= ch && ch <= hi,
+            ClassItem::Digit(neg) => ch.is_ascii_digit() != neg,
+            ClassItem::Word(neg) => (ch.is_alphanumeric() || ch == '_') != neg,
+            ClassItem::Space(neg) => ch.is_whitespace() != neg,
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, ch: char) -> bool {
+        self.items.iter().any(|item| item.matches(ch)) != self.negated
+    }
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Self, String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut parser = Parser { chars: &chars, pos: 0 };
+        let alts = parser.parse_alts()?;
+        if parser.pos < chars.len() {
+            return Err(format!("unmatched ')' in pattern `{source}`"));
+        }
+        let mut compiler = Compiler { prog: Vec::new() };
+        compiler.alts(&alts);
+        compiler.prog.push(Inst::Match);
+        if compiler.prog.len() > MAX_PROGRAM {
+            return Err(format!("pattern `{source}` is too large once repetitions are expanded"));
+        }
+        Ok(Self { prog: compiler.prog })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let mut current = Threads::new(self.prog.len());
+        let mut next = Threads::new(self.prog.len());
+        for pos in 0..=chars.len() {
+            // Unanchored: a fresh attempt joins the live threads at every position.
+            if self.add_thread(&mut current, 0, pos, chars.len()) {
+                return true;
+            }
+            let Some(&ch) = chars.get(pos) else {
+                break;
+            };
+            next.clear();
+            for i in 0..current.list.len() {
+                let pc = current.list[i];
+                let consumed = match &self.prog[pc] {
+                    Inst::Char(lit) => *lit == ch,
+                    Inst::Any => true,
+                    Inst::Class(class) => class.matches(ch),
+                    _ => false,
+                };
+                if consumed && self.add_thread(&mut next, pc + 1, pos + 1, chars.len()) {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        false
+    }
+
+    // Add `pc` and everything reachable from it without consuming a
+    // character; true once the match instruction is reached.
+    fn add_thread(&self, threads: &mut Threads, pc: usize, pos: usize, len: usize) -> bool {
+        threads.stack.push(pc);
+        while let Some(pc) = threads.stack.pop() {
+            if threads.seen[pc] {
+                continue;
+            }
+            threads.seen[pc] = true;
+            threads.list.push(pc);
+            match self.prog[pc] {
+                Inst::Jump(to) => threads.stack.push(to),
+                Inst::Split(a, b) => threads.stack.extend([b, a]),
+                Inst::Start if pos == 0 => threads.stack.push(pc + 1),
+                Inst::End if pos == len => threads.stack.push(pc + 1),
+                Inst::Match => {
+                    threads.stack.clear();
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+}
+
+// The program counters live at one text position, deduplicated so each
+// instruction runs at most once per position.
+struct Threads {
+    list: Vec<usize>,
+    seen: Vec<bool>,
+    stack: Vec<usize>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            list: Vec::new(),
+            seen: vec![false; len],
+            stack: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        for &pc in &self.list {
+            self.seen[pc] = false;
+        }
+        self.list.clear();
+    }
+}
+
+struct Compiler {
+    prog: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> usize {
+        self.prog.push(inst);
+        self.prog.len() - 1
+    }
+
+    fn alts(&mut self, alts: &[Vec<Node>]) {
+        let mut jumps = Vec::new();
+        for (i, alt) in alts.iter().enumerate() {
+            if i + 1 == alts.len() {
+                self.seq(alt);
+                break;
+            }
+            let split = self.emit(Inst::Split(0, 0));
+            self.seq(alt);
+            jumps.push(self.emit(Inst::Jump(0)));
+            self.prog[split] = Inst::Split(split + 1, self.prog.len());
+        }
+        let end = self.prog.len();
+        for jump in jumps {
+            self.prog[jump] = Inst::Jump(end);
+        }
+    }
+
+    fn seq(&mut self, seq: &[Node]) {
+        for node in seq {
+            self.node(node);
+            // Stop expanding a runaway repetition; `Pattern::new` reports it.
+            if self.prog.len() > MAX_PROGRAM {
+                return;
+            }
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Literal(lit) => {
+                self.emit(Inst::Char(*lit));
+            }
+            Node::Any => {
+                self.emit(Inst::Any);
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()));
+            }
+            Node::Start => {
+                self.emit(Inst::Start);
+            }
+            Node::End => {
+                self.emit(Inst::End);
+            }
+            Node::Group(alts) => self.alts(alts),
+            Node::Repeat { node, min, max } => {
+                for _ in 0..*min {
+                    if self.prog.len() > MAX_PROGRAM {
+                        return;
+                    }
+                    self.node(node);
+                }
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0));
+                        self.node(node);
+                        self.emit(Inst::Jump(split));
+                        self.prog[split] = Inst::Split(split + 1, self.prog.len());
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            if self.prog.len() > MAX_PROGRAM {
+                                return;
+                            }
+                            splits.push(self.emit(Inst::Split(0, 0)));
+                            self.node(node);
+                        }
+                        let end = self.prog.len();
+                        for split in splits {
+                            self.prog[split] = Inst::Split(split + 1, end);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += 1;
+        Some(ch)
+    }
+
+    fn parse_alts(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alts = vec![self.parse_seq()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alts.push(self.parse_seq()?);
+        }
+        Ok(alts)
+    }
+
+    fn parse_seq(&mut self) -> Result<Vec<Node>, String> {
+        let mut seq = Vec::new();
+        while let Some(ch) = self.peek() {
+            if ch == '|' || ch == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            seq.push(self.parse_quantifier(atom)?);
+        }
+        Ok(seq)
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let ch = self.bump().ok_or("unexpected end of pattern")?;
+        Ok(match ch {
+            '(' => {
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let alts = self.parse_alts()?;
+                if self.bump() != Some(')') {
+                    return Err("unclosed '(' in pattern".to_string());
+                }
+                Node::Group(alts)
+            }
+            '[' => Node::Class(self.parse_class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => match self.parse_escape()? {
+                Ok(item) => Node::Class(Class {
+                    negated: false,
+                    items: vec![item],
+                }),
+                Err(lit) => Node::Literal(lit),
+            },
+            '*' | '+' | '?' | '{' => {
+                return Err(format!("quantifier '{ch}' has nothing to repeat"));
+            }
+            lit => Node::Literal(lit),
+        })
+    }
+
+    // Returns a class item for `\d`-style escapes, or the escaped literal.
+    fn parse_escape(&mut self) -> Result<Result<ClassItem, char>, String> {
+        let ch = self.bump().ok_or("trailing '\\' in pattern")?;
+        Ok(match ch {
+            'd' => Ok(ClassItem::Digit(false)),
+            'D' => Ok(ClassItem::Digit(true)),
+            'w' => Ok(ClassItem::Word(false)),
+            'W' => Ok(ClassItem::Word(true)),
+            's' => Ok(ClassItem::Space(false)),
+            'S' => Ok(ClassItem::Space(true)),
+            't' => Err('\t'),
+            'n' => Err('\n'),
+            other => Err(other),
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<Class, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let ch = self.bump().ok_or("unclosed '[' in pattern")?;
+            if ch == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = if ch == '\\' {
+                match self.parse_escape()? {
+                    Ok(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Err(lit) => lit,
+                }
+            } else {
+                ch
+            };
+            let is_range = self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']');
+            if is_range {
+                self.pos += 1;
+                let hi = match self.bump() {
+                    Some('\\') => match self.parse_escape()? {
+                        Err(lit) => lit,
+                        Ok(_) => return Err("class escape cannot end a range".to_string()),
+                    },
+                    Some(hi) => hi,
+                    None => return Err("unclosed '[' in pattern".to_string()),
+                };
+                if hi < lo {
+                    return Err(format!("invalid range '{lo}-{hi}' in pattern"));
+                }
+                items.push(ClassItem::Range(lo, hi));
+            } else {
+                items.push(ClassItem::Range(lo, lo));
+            }
+        }
+        Ok(Class { negated, items })
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => return self.parse_braces(atom),
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        Ok(repeat(atom, min, max))
+    }
+
+    fn parse_braces(&mut self, atom: Node) -> Result<Node, String> {
+        let close = self.chars[self.pos..]
+            .iter()
+            .position(|c| *c == '}')
+            .ok_or("unclosed '{' in pattern")?;
+        let body: String = self.chars[self.pos + 1..self.pos + close].iter().collect();
+        let bad = || format!("invalid repetition '{{{body}}}' in pattern");
+        let (min, max) = match body.split_once(',') {
+            None => {
+                let n = body.parse().map_err(|_| bad())?;
+                (n, Some(n))
+            }
+            Some((lo, "")) => (lo.parse().map_err(|_| bad())?, None),
+            Some((lo, hi)) => (
+                lo.parse().map_err(|_| bad())?,
+                Some(hi.parse().map_err(|_| bad())?),
+            ),
+        };
+        if max.is_some_and(|m| m < min) {
+            return Err(bad());
+        }
+        self.pos += close + 1;
+        Ok(repeat(atom, min, max))
+    }
+}
+
+fn repeat(atom: Node, min: u32, max: Option<u32>) -> Node {
+    Node::Repeat {
+        node: Box::new(atom),
+        min,
+        max,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Pattern::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn matches_the_supported_syntax() {
+        assert!(matches("^(cargo|git)", "git status"));
+        assert!(!matches("^(cargo|git)", "npm run git"));
+        assert!(matches(r"^\s*#", "  # comment"));
+        assert!(matches("[a-c]+d$", "xxabcd"));
+        assert!(!matches("[^a-c]d$", "cd"));
+        assert!(matches(r"\d{2,3}", "exit 42"));
+        assert!(!matches(r"^\d{2,3}$", "4242"));
+        assert!(matches("colou?r", "color"));
+        assert!(matches("a.c", "abc"));
+        assert!(matches("", "anything"));
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        assert!(Pattern::new("(a").is_err());
+        assert!(Pattern::new("a)").is_err());
+        assert!(Pattern::new("*a").is_err());
+        assert!(Pattern::new("[z-a]").is_err());
+        assert!(Pattern::new("a{3,1}").is_err());
+        assert!(Pattern::new("(a{1000}){1000}").is_err());
+    }
+
+    #[test]
+    fn long_adversarial_input_matches_quickly() {
+        let text = "a".repeat(100_000);
+        assert!(!matches("(a*)*b", &text));
+        assert!(!matches("(a|aa)+$x", &text));
+        assert!(matches("(a*)*$", &text));
+    }
+}
@@ -1,12 +1,859 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ratatui::style::Color;
+
+use crate::commands::Pattern;
+use crate::project;
+use crate::game::{decode_snapshot, load_scenario, Action, PowerUp, Scenario, Shape, Snapshot, TopOut, ROTATIONS};
+
 // Shared game UI/constants.
 pub const BOARD_W: usize = 10;
 pub const BOARD_H: usize = 20;
-pub const CELL_W: usize = 2; // render each block as two characters wide (letter + filler)
+pub const CELL_W: usize = 2; // default block width in characters (letter + filler), see `Config::cell_w`
 pub const PLAY_W: usize = BOARD_W * CELL_W + 2; // inner width plus side walls
 pub const PLAY_H: usize = BOARD_H + 2; // inner height plus ceiling/floor
+// Columns the INFO and CONTROLS boxes reach past the well on each side.
+pub const PANEL_EXPAND: u16 = 8;
 // Minimal pane width to fit the expanded info/controls width + cabinet border.
-pub const MIN_PANE_WIDTH: u16 = (PLAY_W as u16) + 2;
+pub const MIN_PANE_WIDTH: u16 = min_pane_width(CELL_W);
 pub const CHUNK_SIZE: usize = 8;
-pub const SOCKET_PATH: &str = "/tmp/stack-game.sock";
+pub const SOCKET_PATH: &str = crate::project::DEFAULT_SOCKET;
 pub const VARIETY_THRESH: i32 = 100;
 pub const BOMB_CAP: i32 = 3;
+pub const LINES_PER_LEVEL: u64 = 10;
+pub const GRAVITY_MS: u64 = 450;
+
+pub const fn min_pane_width(cell_w: usize) -> u16 {
+    (BOARD_W * cell_w + 2) as u16 + 2 * PANEL_EXPAND + 2
+}
+
+// Runtime tunables, read once at startup from `STACK_*` environment variables.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    pub spawn_collision: SpawnCollision,
+    pub top_out: TopOutRule,
+    pub ending: Ending,
+    pub pressure_gravity: bool,
+    pub time_limit: Option<Duration>,
+    pub snapshot: Option<Snapshot>,
+    // Rotation each shape spawns in, indexed by `Shape::index`.
+    pub spawn_rotation: [u8; Shape::ALL.len()],
+    pub seed: Option<u64>,
+    pub record: Option<PathBuf>,
+    // Cells cleared around each bomb cell: 1 is a 3x3 blast, 2 is 5x5, ...
+    pub bomb_radius: i32,
+    pub bomb_shape: Shape,
+    // Leaderboard runs: no bombs and no ghost piece, so scores are comparable.
+    pub ironman: bool,
+    pub aliases: HashMap<String, String>,
+    pub command_actions: HashMap<String, Action>,
+    // Add a garbage row per this many stderr lines a command printed, even on
+    // success. Off unless set; needs a hook that reports `stderr=`.
+    pub stderr_lines_per_row: Option<u64>,
+    pub are_frames: u8,
+    pub tilt: bool,
+    pub failure_variety: FailureVariety,
+    // Blitz: once this many distinct commands finish within the window, each
+    // further different one earns the bonus on top of its streak points.
+    pub blitz_window: Option<Duration>,
+    pub blitz_min: usize,
+    pub blitz_bonus: i32,
+    pub clear_variety: i32,
+    pub gravity: GravityDir,
+    pub behind_warning: bool,
+    pub caution_height: Option<usize>,
+    pub danger_height: Option<usize>,
+    pub infection_per_failure: usize,
+    pub infection_cap: Option<usize>,
+    pub spawn_grace_frames: u8,
+    pub first_spawn_grace_frames: u8,
+    pub fail_flash_frames: u8,
+    // Accessibility: draw effects as steady markers instead of flashes and
+    // animation. Only the drawing changes; effect timing is the same.
+    pub reduced_motion: bool,
+    pub garbage_rise_frames: u8,
+    // Frames a grounded piece waits before locking (0 locks on the gravity
+    // tick), and how many moves or rotations may restart that wait per piece.
+    pub lock_delay_frames: u8,
+    pub lock_reset_cap: u32,
+    pub max_pieces_per_cycle: Option<usize>,
+    pub merge_window: Option<Duration>,
+    pub slow_command: Option<Duration>,
+    pub idle_pause: Option<Duration>,
+    pub line_goal: Option<u64>,
+    // Interval between soft-drop steps while the key is held; the terminal's
+    // own key repeat when unset.
+    pub soft_drop_rate: Option<Duration>,
+    pub render_fps: u32,
+    pub game_over_dir: Option<PathBuf>,
+    pub scenario: Option<Scenario>,
+    pub scenario_dir: PathBuf,
+    // With garbage on the board, one variety payout in this many clears all
+    // garbage rows instead of granting a bomb; 0 means always a bomb.
+    pub garbage_clear_odds: u32,
+    pub rewards: Option<RewardTable>,
+    // What a failure does by exit code, first matching range wins; codes
+    // not listed get `ExitEffect::default()`.
+    pub exit_effects: Vec<(i32, i32, ExitEffect)>,
+    // Pieces kept queued ahead of the falling one while commands run, so the
+    // preview can show that far; 0 refills only when the queue runs dry.
+    pub preview_lead: usize,
+    pub min_pane_width: u16,
+    pub narrow_layout: NarrowLayout,
+    pub terminal_fallback: TerminalFallback,
+    // Characters per block across. Terminal cells are about twice as tall as
+    // wide, so 2 looks square on most fonts; narrow fonts may want 3.
+    pub cell_w: usize,
+    pub infection_glyph: char,
+    pub infection_color: Option<Color>,
+    pub infection_shimmer: bool,
+    pub debug_commands: bool,
+    pub socket_path: String,
+    pub score_format: ScoreFormat,
+    pub score_count_frames: u8,
+    // The settings this was read from, less `LOCAL_SETTINGS`: what a
+    // recorded log carries so its replay plays by the same rules.
+    pub settings: Settings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            spawn_collision: SpawnCollision::default(),
+            top_out: TopOutRule::default(),
+            ending: Ending::default(),
+            pressure_gravity: false,
+            time_limit: None,
+            snapshot: None,
+            spawn_rotation: [0; Shape::ALL.len()],
+            seed: None,
+            record: None,
+            bomb_radius: 1,
+            bomb_shape: Shape::O,
+            ironman: false,
+            aliases: HashMap::new(),
+            command_actions: HashMap::new(),
+            stderr_lines_per_row: None,
+            are_frames: 0,
+            tilt: false,
+            failure_variety: FailureVariety::default(),
+            blitz_window: None,
+            blitz_min: 3,
+            blitz_bonus: 15,
+            clear_variety: 0,
+            gravity: GravityDir::default(),
+            behind_warning: false,
+            caution_height: None,
+            danger_height: None,
+            infection_per_failure: 5,
+            infection_cap: None,
+            spawn_grace_frames: 0,
+            first_spawn_grace_frames: 0,
+            fail_flash_frames: 0,
+            reduced_motion: false,
+            garbage_rise_frames: 0,
+            lock_delay_frames: 0,
+            lock_reset_cap: 15,
+            max_pieces_per_cycle: None,
+            slow_command: None,
+            idle_pause: None,
+            merge_window: None,
+            line_goal: None,
+            soft_drop_rate: None,
+            render_fps: 20,
+            game_over_dir: None,
+            scenario: None,
+            scenario_dir: PathBuf::from("scenarios"),
+            garbage_clear_odds: 3,
+            rewards: None,
+            exit_effects: Vec::new(),
+            preview_lead: 0,
+            min_pane_width: MIN_PANE_WIDTH,
+            narrow_layout: NarrowLayout::default(),
+            terminal_fallback: TerminalFallback::default(),
+            cell_w: CELL_W,
+            infection_glyph: '?',
+            infection_color: Some(Color::LightGreen),
+            infection_shimmer: true,
+            debug_commands: cfg!(debug_assertions),
+            socket_path: SOCKET_PATH.to_string(),
+            score_format: ScoreFormat::default(),
+            score_count_frames: 8,
+            settings: Settings::new(),
+        }
+    }
+}
+
+// What happens when a freshly spawned piece overlaps the stack.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpawnCollision {
+    #[default]
+    TopOut,
+    // Clear the rows under the spawn once, and only top out if it still collides.
+    Crush,
+}
+
+// Which top-outs end the game. With `BlockOut`, garbage pushed up through
+// the ceiling is simply lost; with `LockOut`, a blocked spawn crushes the rows
+// under it instead, as `SpawnCollision::Crush` does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TopOutRule {
+    BlockOut,
+    LockOut,
+    #[default]
+    Both,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Ending {
+    #[default]
+    Freeze,
+    // The pieces already queued keep falling and locking, unscored, and the
+    // summary shows once the queue drains or a spawn is blocked.
+    Cinematic,
+}
+
+impl TopOutRule {
+    pub fn ends_game(self, top_out: TopOut) -> bool {
+        matches!(
+            (self, top_out),
+            (TopOutRule::Both, _)
+                | (TopOutRule::BlockOut, TopOut::BlockOut)
+                | (TopOutRule::LockOut, TopOut::LockOut)
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScoreFormat {
+    #[default]
+    Plain,
+    Grouped,
+    Short,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TerminalFallback {
+    #[default]
+    Lines,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NarrowLayout {
+    #[default]
+    Hide,
+    Stack,
+    Inline,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RewardTable {
+    Rotation(Vec<PowerUp>),
+    Weighted(Vec<(PowerUp, u32)>),
+}
+
+// The penalty for one failed command. Any penalty also flashes the well and
+// counts toward the tilt streak; `NONE` is a deliberate cancel, no failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExitEffect {
+    pub garbage_rows: u64,
+    pub infect: bool,
+}
+
+impl ExitEffect {
+    pub const NONE: ExitEffect = ExitEffect {
+        garbage_rows: 0,
+        infect: false,
+    };
+
+    pub fn is_penalty(self) -> bool {
+        self != Self::NONE
+    }
+}
+
+impl Default for ExitEffect {
+    fn default() -> Self {
+        Self {
+            garbage_rows: 1,
+            infect: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailureVariety {
+    Full,
+    #[default]
+    Half,
+    Zero,
+}
+
+// Which way pieces fall. With `Up` they spawn at the floor, rise, and stack
+// against the ceiling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GravityDir {
+    #[default]
+    Down,
+    Up,
+}
+
+impl GravityDir {
+    pub fn dy(self) -> i32 {
+        match self {
+            GravityDir::Down => 1,
+            GravityDir::Up => -1,
+        }
+    }
+}
+
+pub type Settings = BTreeMap<String, String>;
+
+// Paths and addresses that only make sense on the machine that set them, so
+// a recorded log's header leaves them out.
+const LOCAL_SETTINGS: &[&str] = &["STACK_RECORD", "STACK_GAME_OVER_DIR", "STACK_PROJECT", "STACK_SOCK", "STACK_LOG", "STACK_LOG_FILE"];
+
+impl Config {
+    pub fn from_env() -> Result<Self, String> {
+        let vars = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(name, _)| name.starts_with("STACK_") || name == "NO_COLOR")
+            .collect();
+        Self::from_settings(&vars)
+    }
+
+    pub fn from_settings(vars: &Settings) -> Result<Self, String> {
+        let mut config = Self::default();
+        if let Some(src) = env_var(vars, "STACK_INCLUDE") {
+            config.include = parse_patterns("STACK_INCLUDE", &src)?;
+        }
+        if let Some(src) = env_var(vars, "STACK_EXCLUDE") {
+            config.exclude = parse_patterns("STACK_EXCLUDE", &src)?;
+        }
+        if let Some(mode) = env_choice(
+            vars,
+            "STACK_SPAWN_COLLISION",
+            &[("top-out", SpawnCollision::TopOut), ("crush", SpawnCollision::Crush)],
+        )? {
+            config.spawn_collision = mode;
+        }
+        if let Some(rule) = env_choice(
+            vars,
+            "STACK_TOP_OUT",
+            &[("block-out", TopOutRule::BlockOut), ("lock-out", TopOutRule::LockOut), ("both", TopOutRule::Both)],
+        )? {
+            config.top_out = rule;
+        }
+        if let Some(ending) = env_choice(vars, "STACK_ENDING", &[("freeze", Ending::Freeze), ("cinematic", Ending::Cinematic)])? {
+            config.ending = ending;
+        }
+        if let Some(on) = env_flag(vars, "STACK_PRESSURE")? {
+            config.pressure_gravity = on;
+        }
+        config.time_limit = env_duration(vars, "STACK_TIME_LIMIT")?;
+        config.slow_command = env_duration(vars, "STACK_SLOW_COMMAND")?;
+        config.idle_pause = env_duration(vars, "STACK_IDLE_PAUSE")?;
+        config.merge_window = env_duration(vars, "STACK_MERGE_WINDOW")?;
+        config.blitz_window = env_duration(vars, "STACK_BLITZ_WINDOW")?;
+        if let Some(min) = env_number(vars, "STACK_BLITZ_MIN", 2)? {
+            config.blitz_min = min;
+        }
+        if let Some(bonus) = env_number(vars, "STACK_BLITZ_BONUS", 0)? {
+            config.blitz_bonus = bonus;
+        }
+        if let Some(points) = env_number(vars, "STACK_CLEAR_VARIETY", 0)? {
+            config.clear_variety = points;
+        }
+        config.line_goal = env_number(vars, "STACK_LINE_GOAL", 1)?;
+        if let Some(layout) = env_choice(
+            vars,
+            "STACK_NARROW_LAYOUT",
+            &[("hide", NarrowLayout::Hide), ("stack", NarrowLayout::Stack), ("inline", NarrowLayout::Inline)],
+        )? {
+            config.narrow_layout = layout;
+        }
+        if let Some(fallback) = env_choice(
+            vars,
+            "STACK_TERMINAL_FALLBACK",
+            &[("lines", TerminalFallback::Lines), ("error", TerminalFallback::Error)],
+        )? {
+            config.terminal_fallback = fallback;
+        }
+        if let Some(format) = env_choice(
+            vars,
+            "STACK_SCORE_FORMAT",
+            &[("plain", ScoreFormat::Plain), ("grouped", ScoreFormat::Grouped), ("short", ScoreFormat::Short)],
+        )? {
+            config.score_format = format;
+        }
+        if let Some(frames) = env_number(vars, "STACK_SCORE_COUNT", 0)? {
+            config.score_count_frames = frames;
+        }
+        if let Some(width) = env_number(vars, "STACK_CELL_W", CELL_W)? {
+            config.cell_w = width;
+        }
+        // Wider blocks need a wider pane before the boxes fit beside the well.
+        config.min_pane_width = match env_number(vars, "STACK_MIN_PANE_WIDTH", 0)? {
+            Some(width) => width,
+            None => min_pane_width(config.cell_w),
+        };
+        if let Some(glyph) = env_var(vars, "STACK_INFECTION_GLYPH") {
+            config.infection_glyph = parse_glyph(&glyph).ok_or("STACK_INFECTION_GLYPH: expected a single character")?;
+        }
+        if let Some(color) = env_var(vars, "STACK_INFECTION_COLOR") {
+            config.infection_color = match color.trim() {
+                "none" => None,
+                name => Some(name.parse().map_err(|_| format!("STACK_INFECTION_COLOR: unknown color `{name}`"))?),
+            };
+        }
+        // https://no-color.org: any value turns color off.
+        if env_var(vars, "NO_COLOR").is_some() {
+            config.infection_color = None;
+        }
+        if let Some(on) = env_flag(vars, "STACK_INFECTION_SHIMMER")? {
+            config.infection_shimmer = on;
+        }
+        if let Some(lead) = env_number(vars, "STACK_PREVIEW_LEAD", 0)? {
+            config.preview_lead = lead;
+        }
+        config.soft_drop_rate = env_duration(vars, "STACK_SOFT_DROP_RATE")?;
+        if let Some(fps) = env_number(vars, "STACK_FPS", 1)? {
+            config.render_fps = fps;
+        }
+        if let Some(spec) = env_var(vars, "STACK_SPAWN_ROTATION") {
+            config.spawn_rotation = parse_spawn_rotation(&spec)?;
+        }
+        config.seed = env_number(vars, "STACK_SEED", 0)?;
+        config.record = env_var(vars, "STACK_RECORD").map(PathBuf::from);
+        config.game_over_dir = env_var(vars, "STACK_GAME_OVER_DIR").map(PathBuf::from);
+        if let Some(project) = env_var(vars, "STACK_PROJECT") {
+            config.socket_path = project::socket_path(Some(&project));
+        }
+        if let Some(path) = env_var(vars, "STACK_SOCK") {
+            config.socket_path = path;
+        }
+        if let Some(dir) = env_var(vars, "STACK_SCENARIO_DIR") {
+            config.scenario_dir = PathBuf::from(dir);
+        }
+        if let Some(name) = env_var(vars, "STACK_SCENARIO") {
+            config.scenario = Some(load_scenario(&config.scenario_dir, &name)?);
+        }
+        if let Some(radius) = env_number(vars, "STACK_BOMB_RADIUS", 1)? {
+            config.bomb_radius = radius;
+        }
+        if let Some(shape) = env_choice(
+            vars,
+            "STACK_BOMB_SHAPE",
+            &[
+                ("i", Shape::I),
+                ("o", Shape::O),
+                ("t", Shape::T),
+                ("s", Shape::S),
+                ("z", Shape::Z),
+                ("j", Shape::J),
+                ("l", Shape::L),
+            ],
+        )? {
+            config.bomb_shape = shape;
+        }
+        if let Some(rule) = env_choice(
+            vars,
+            "STACK_FAILURE_VARIETY",
+            &[("full", FailureVariety::Full), ("half", FailureVariety::Half), ("zero", FailureVariety::Zero)],
+        )? {
+            config.failure_variety = rule;
+        }
+        if let Some(dir) = env_choice(vars, "STACK_GRAVITY", &[("down", GravityDir::Down), ("up", GravityDir::Up)])? {
+            config.gravity = dir;
+        }
+        if let Some(on) = env_flag(vars, "STACK_BEHIND_WARNING")? {
+            config.behind_warning = on;
+        }
+        config.caution_height = env_number(vars, "STACK_CAUTION_HEIGHT", 1)?;
+        config.danger_height = env_number(vars, "STACK_DANGER_HEIGHT", 1)?;
+        if let (Some(caution), Some(danger)) = (config.caution_height, config.danger_height)
+            && caution >= danger
+        {
+            return Err(format!(
+                "STACK_CAUTION_HEIGHT: {caution} must be below STACK_DANGER_HEIGHT ({danger})"
+            ));
+        }
+        if let Some(count) = env_number(vars, "STACK_INFECTION", 0)? {
+            config.infection_per_failure = count;
+        }
+        config.infection_cap = env_number(vars, "STACK_INFECTION_CAP", 0)?;
+        if let Some(odds) = env_number(vars, "STACK_GARBAGE_CLEAR", 0)? {
+            config.garbage_clear_odds = odds;
+        }
+        config.max_pieces_per_cycle = env_number(vars, "STACK_MAX_PIECES", 1)?;
+        if let Some(on) = env_flag(vars, "STACK_DEBUG_COMMANDS")? {
+            config.debug_commands = on;
+        }
+        if let Some(on) = env_flag(vars, "STACK_TILT")? {
+            config.tilt = on;
+        }
+        if let Some(on) = env_flag(vars, "STACK_IRONMAN")? {
+            config.ironman = on;
+        }
+        if let Some(spec) = env_var(vars, "STACK_REWARDS") {
+            config.rewards = Some(parse_rewards(&spec)?);
+        }
+        if let Some(spec) = env_var(vars, "STACK_EXIT_EFFECTS") {
+            config.exit_effects = parse_exit_effects(&spec)?;
+        }
+        if let Some(spec) = env_var(vars, "STACK_ALIASES") {
+            config.aliases = parse_aliases(&spec)?;
+        }
+        if let Some(spec) = env_var(vars, "STACK_COMMAND_ACTIONS") {
+            config.command_actions = parse_command_actions(&spec)?;
+        }
+        config.stderr_lines_per_row = env_number(vars, "STACK_STDERR_GARBAGE", 1)?;
+        if let Some(frames) = env_number(vars, "STACK_ARE", 0)? {
+            config.are_frames = frames;
+        }
+        if let Some(frames) = env_number(vars, "STACK_SPAWN_GRACE", 0)? {
+            config.spawn_grace_frames = frames;
+        }
+        if let Some(frames) = env_number(vars, "STACK_FIRST_GRACE", 0)? {
+            config.first_spawn_grace_frames = frames;
+        }
+        if let Some(on) = env_flag(vars, "STACK_REDUCED_MOTION")? {
+            config.reduced_motion = on;
+        }
+        if let Some(frames) = env_number(vars, "STACK_FAIL_FLASH", 0)? {
+            config.fail_flash_frames = frames;
+        }
+        if let Some(frames) = env_number(vars, "STACK_GARBAGE_RISE", 0)? {
+            config.garbage_rise_frames = frames;
+        }
+        if let Some(frames) = env_number(vars, "STACK_LOCK_DELAY", 0)? {
+            config.lock_delay_frames = frames;
+        }
+        if let Some(cap) = env_number(vars, "STACK_LOCK_RESETS", 0)? {
+            config.lock_reset_cap = cap;
+        }
+        if let Some(text) = env_var(vars, "STACK_SNAPSHOT") {
+            config.snapshot = Some(decode_snapshot(&text).map_err(|e| format!("STACK_SNAPSHOT: {e}"))?);
+        }
+        config.settings = vars
+            .iter()
+            .filter(|(name, value)| !value.trim().is_empty() && !LOCAL_SETTINGS.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        Ok(config)
+    }
+
+    pub fn exit_effect(&self, code: i32) -> ExitEffect {
+        self.exit_effects
+            .iter()
+            .find(|&&(low, high, _)| (low..=high).contains(&code))
+            .map_or_else(ExitEffect::default, |&(_, _, effect)| effect)
+    }
+
+    // A non-zero exit mapped to `none` was cancelled on purpose, not failed.
+    pub fn is_failure(&self, code: i32) -> bool {
+        code != 0 && self.exit_effect(code).is_penalty()
+    }
+
+    pub fn counts_command(&self, command: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.is_match(command));
+        included && !self.exclude.iter().any(|p| p.is_match(command))
+    }
+}
+
+fn env_var(vars: &Settings, name: &str) -> Option<String> {
+    vars.get(name).filter(|v| !v.trim().is_empty()).cloned()
+}
+
+fn env_number<T>(vars: &Settings, name: &str, min: T) -> Result<Option<T>, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let Some(value) = env_var(vars, name) else {
+        return Ok(None);
+    };
+    match value.trim().parse::<T>() {
+        Ok(n) if n >= min => Ok(Some(n)),
+        _ => Err(format!("{name}: expected a number >= {min}, got `{value}`")),
+    }
+}
+
+// `I=1,T=2`: shape letter to rotation index; unlisted shapes keep rotation 0.
+fn parse_spawn_rotation(spec: &str) -> Result<[u8; Shape::ALL.len()], String> {
+    let mut rotations = [0; Shape::ALL.len()];
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let bad = || format!("STACK_SPAWN_ROTATION: expected `SHAPE=ROTATION`, got `{item}`");
+        let (letter, rotation) = item.split_once('=').ok_or_else(bad)?;
+        let mut letters = letter.trim().chars();
+        let shape = match (letters.next(), letters.next()) {
+            (Some(ch), None) => Shape::from_letter(ch).ok_or_else(bad)?,
+            _ => return Err(bad()),
+        };
+        let rotation: u8 = rotation.trim().parse().map_err(|_| bad())?;
+        if rotation >= ROTATIONS {
+            return Err(format!(
+                "STACK_SPAWN_ROTATION: rotation {rotation} for {} is out of range 0-{}",
+                shape.letter(),
+                ROTATIONS - 1
+            ));
+        }
+        rotations[shape.index()] = rotation;
+    }
+    Ok(rotations)
+}
+
+// `ALIAS=NAME` pairs, comma separated: `g=git,k=kubectl`.
+fn parse_aliases(spec: &str) -> Result<HashMap<String, String>, String> {
+    let mut aliases = HashMap::new();
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let bad = || format!("STACK_ALIASES: expected `ALIAS=NAME`, got `{item}`");
+        let (alias, name) = item.split_once('=').ok_or_else(bad)?;
+        let (alias, name) = (alias.trim(), name.trim());
+        if alias.is_empty() || name.is_empty() {
+            return Err(bad());
+        }
+        aliases.insert(alias.to_string(), name.to_string());
+    }
+    Ok(aliases)
+}
+
+fn parse_command_actions(spec: &str) -> Result<HashMap<String, Action>, String> {
+    let mut actions = HashMap::new();
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let bad = || format!("STACK_COMMAND_ACTIONS: expected `COMMAND=ACTION`, got `{item}`");
+        let (command, name) = item.split_once('=').ok_or_else(bad)?;
+        let command = command.trim();
+        if command.is_empty() {
+            return Err(bad());
+        }
+        let action = Action::from_name(name.trim())
+            .ok_or_else(|| format!("STACK_COMMAND_ACTIONS: unknown action `{}`", name.trim()))?;
+        actions.insert(command.to_string(), action);
+    }
+    Ok(actions)
+}
+
+// `bomb,freeze,bomb` grants in that order, round and round; any `NAME:WEIGHT`
+// (`bomb:3,shuffle:1`) makes it a weighted draw, unweighted entries counting 1.
+fn parse_rewards(spec: &str) -> Result<RewardTable, String> {
+    let mut entries = Vec::new();
+    let mut weighted = false;
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let bad = || {
+            format!("STACK_REWARDS: expected `bomb`, `garbage-clear`, `shuffle` or `freeze` with an optional `:WEIGHT`, got `{item}`")
+        };
+        let (name, weight) = match item.split_once(':') {
+            Some((name, weight)) => {
+                weighted = true;
+                (name.trim(), weight.trim().parse::<u32>().ok().filter(|&w| w > 0).ok_or_else(bad)?)
+            }
+            None => (item, 1),
+        };
+        entries.push((PowerUp::from_name(name).ok_or_else(bad)?, weight));
+    }
+    if entries.is_empty() {
+        return Err("STACK_REWARDS: expected at least one power-up".to_string());
+    }
+    Ok(if weighted {
+        RewardTable::Weighted(entries)
+    } else {
+        RewardTable::Rotation(entries.into_iter().map(|(power_up, _)| power_up).collect())
+    })
+}
+
+// `130=none,124=infection,137=garbage:3+infection,2-99=garbage`: an exit code
+// or inclusive range, then `none` or effects joined by `+`.
+fn parse_exit_effects(spec: &str) -> Result<Vec<(i32, i32, ExitEffect)>, String> {
+    let mut table = Vec::new();
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let bad = || format!("STACK_EXIT_EFFECTS: expected `CODE[-CODE]=EFFECT`, got `{item}`");
+        let (codes, effects) = item.split_once('=').ok_or_else(bad)?;
+        let (low, high) = codes.split_once('-').unwrap_or((codes, codes));
+        let (low, high): (i32, i32) = match (low.trim().parse(), high.trim().parse()) {
+            (Ok(low), Ok(high)) if low <= high => (low, high),
+            _ => return Err(bad()),
+        };
+        let mut effect = ExitEffect::NONE;
+        for term in effects.split('+').map(str::trim) {
+            match term {
+                "none" => {}
+                "infection" => effect.infect = true,
+                "garbage" => effect.garbage_rows = 1,
+                _ => {
+                    let rows = term.strip_prefix("garbage:").ok_or_else(|| {
+                        format!("STACK_EXIT_EFFECTS: unknown effect `{term}` (expected `none`, `garbage[:ROWS]` or `infection`)")
+                    })?;
+                    effect.garbage_rows = rows.trim().parse().map_err(|_| bad())?;
+                }
+            }
+        }
+        table.push((low, high, effect));
+    }
+    Ok(table)
+}
+
+// Accepts plain seconds (`90`) or a unit suffix: `1500ms`, `90s`, `2m`.
+fn env_duration(vars: &Settings, name: &str) -> Result<Option<Duration>, String> {
+    env_var(vars, name).map(|value| parse_duration(name, &value)).transpose()
+}
+
+fn parse_duration(name: &str, value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (digits, scale_ms) = if let Some(n) = value.strip_suffix("ms") {
+        (n, 1)
+    } else if let Some(n) = value.strip_suffix('s') {
+        (n, 1_000)
+    } else if let Some(n) = value.strip_suffix('m') {
+        (n, 60_000)
+    } else {
+        (value, 1_000)
+    };
+    match digits.trim().parse::<u64>() {
+        Ok(n) if n > 0 => n
+            .checked_mul(scale_ms)
+            .map(Duration::from_millis)
+            .ok_or_else(|| format!("{name}: duration `{value}` is too long")),
+        _ => Err(format!("{name}: expected a positive duration like `90s` or `2m`, got `{value}`")),
+    }
+}
+
+fn env_flag(vars: &Settings, name: &str) -> Result<Option<bool>, String> {
+    env_choice(
+        vars,
+        name,
+        &[
+            ("1", true),
+            ("true", true),
+            ("yes", true),
+            ("on", true),
+            ("0", false),
+            ("false", false),
+            ("no", false),
+            ("off", false),
+        ],
+    )
+}
+
+fn env_choice<T: Copy>(vars: &Settings, name: &str, choices: &[(&str, T)]) -> Result<Option<T>, String> {
+    let Some(value) = env_var(vars, name) else {
+        return Ok(None);
+    };
+    let value = value.trim().to_ascii_lowercase();
+    match choices.iter().find(|(key, _)| *key == value) {
+        Some((_, choice)) => Ok(Some(*choice)),
+        None => {
+            let keys: Vec<&str> = choices.iter().map(|(key, _)| *key).collect();
+            Err(format!("{name}: expected one of {}, got `{value}`", keys.join(", ")))
+        }
+    }
+}
+
+fn parse_glyph(text: &str) -> Option<char> {
+    let mut chars = text.trim().chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+// Several patterns are separated by `;`; a literal semicolon is written `\;`,
+// which the pattern reads as itself.
+fn parse_patterns(name: &str, src: &str) -> Result<Vec<Pattern>, String> {
+    let mut sources = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+    for ch in src.chars() {
+        if ch == ';' && !escaped {
+            sources.push(std::mem::take(&mut current));
+            continue;
+        }
+        escaped = ch == '\\' && !escaped;
+        current.push(ch);
+    }
+    sources.push(current);
+    sources
+        .iter()
+        .map(|source| source.trim())
+        .filter(|source| !source.is_empty())
+        .map(|source| Pattern::new(source).map_err(|e| format!("{name}: {e}")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::config_from;
+
+    #[test]
+    fn patterns_split_on_unescaped_semicolons() {
+        let patterns = parse_patterns("STACK_INCLUDE", r"^cargo; ^git ;a\;b").unwrap();
+        assert_eq!(patterns.len(), 3);
+        assert!(patterns[1].is_match("git push"));
+        assert!(patterns[2].is_match("a;b"));
+        assert!(parse_patterns("STACK_INCLUDE", "ok;(").is_err());
+    }
+
+    #[test]
+    fn durations_parse_units_and_reject_overflow() {
+        assert_eq!(parse_duration("T", "1500ms"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("T", "90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("T", "2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("T", "0s").is_err());
+        assert!(parse_duration("T", "soon").is_err());
+        assert!(parse_duration("T", &format!("{}m", u64::MAX / 2)).unwrap_err().contains("too long"));
+    }
+
+    #[test]
+    fn spawn_rotations_are_validated_per_shape() {
+        let rotations = parse_spawn_rotation("I=1, T=3").unwrap();
+        assert_eq!(rotations[Shape::I.index()], 1);
+        assert_eq!(rotations[Shape::T.index()], 3);
+        assert_eq!(rotations[Shape::O.index()], 0);
+        assert!(parse_spawn_rotation("T=4").is_err());
+        assert!(parse_spawn_rotation("Q=1").is_err());
+    }
+
+    #[test]
+    fn bomb_radius_must_be_at_least_one() {
+        let radius = |value| config_from(&[("STACK_BOMB_RADIUS", value)]);
+        assert_eq!(radius("2").unwrap().bomb_radius, 2);
+        assert!(radius("0").is_err());
+    }
+
+    #[test]
+    fn bomb_shape_accepts_only_known_letters() {
+        let shape = |value| config_from(&[("STACK_BOMB_SHAPE", value)]);
+        assert_eq!(shape("I").unwrap().bomb_shape, Shape::I);
+        assert!(shape("q").is_err());
+    }
+
+    #[test]
+    fn the_pane_width_default_follows_the_cell_width() {
+        assert_eq!(config_from(&[]).unwrap().min_pane_width, MIN_PANE_WIDTH);
+        let wide = config_from(&[("STACK_CELL_W", "3")]).unwrap();
+        assert_eq!(wide.min_pane_width, MIN_PANE_WIDTH + BOARD_W as u16);
+        let pinned = config_from(&[("STACK_CELL_W", "3"), ("STACK_MIN_PANE_WIDTH", "44")]).unwrap();
+        assert_eq!(pinned.min_pane_width, 44);
+    }
+
+    #[test]
+    fn caution_must_sit_below_danger() {
+        let heights = |caution, danger| config_from(&[("STACK_CAUTION_HEIGHT", caution), ("STACK_DANGER_HEIGHT", danger)]);
+        let config = heights("12", "16").unwrap();
+        assert_eq!((config.caution_height, config.danger_height), (Some(12), Some(16)));
+        assert!(heights("16", "16").is_err());
+        assert!(heights("17", "16").unwrap_err().contains("STACK_DANGER_HEIGHT"));
+    }
+
+    #[test]
+    fn no_color_comes_from_the_settings() {
+        assert_eq!(config_from(&[]).unwrap().infection_color, Some(Color::LightGreen));
+        let mono = config_from(&[("NO_COLOR", "1")]).unwrap();
+        assert_eq!(mono.infection_color, None);
+        assert_eq!(mono.settings.get("NO_COLOR").map(String::as_str), Some("1"));
+    }
+}
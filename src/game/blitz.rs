@@ -0,0 +1,24 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// Recent command completions by identity, for the blitz variety bonus.
+#[derive(Default)]
+pub struct BlitzTracker {
+    recent: VecDeque<(Duration, String)>,
+}
+
+impl BlitzTracker {
+    // Note a completion at `at`, forget those older than `window`, and return
+    // how many distinct identities are left in it.
+    pub fn record(&mut self, at: Duration, identity: &str, window: Duration) -> usize {
+        let start = at.saturating_sub(window);
+        while self.recent.front().is_some_and(|(t, _)| *t < start) {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((at, identity.to_string()));
+        let mut seen: Vec<&str> = self.recent.iter().map(|(_, id)| id.as_str()).collect();
+        seen.sort_unstable();
+        seen.dedup();
+        seen.len()
+    }
+}
@@ -1,14 +1,25 @@
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Cell {
     Empty,
     Filled(char, char),
+    Infected,
+    Garbage,
 }
 
-#[derive(Clone)]
+impl Cell {
+    pub fn is_filled(self) -> bool {
+        !matches!(self, Cell::Empty)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Board {
     pub width: usize,
     pub height: usize,
     pub cells: Vec<Cell>,
+    // Run that locked each cell, parallel to `cells`; `None` for garbage,
+    // infection, bombs and cells loaded from a snapshot.
+    pub origins: Vec<Option<u64>>,
 }
 
 impl Board {
@@ -17,7 +28,51 @@ impl Board {
             width,
             height,
             cells: vec![Cell::Empty; width * height],
+            origins: vec![None; width * height],
+        }
+    }
+
+    // A board drawn as text, one line per row: `.` is empty, `#` garbage,
+    // `?` infected, and any other character a locked cell showing it. Fewer
+    // lines than `height` sit at the bottom, as a practice setup would.
+    pub fn from_ascii(text: &str, width: usize, height: usize) -> Result<Self, String> {
+        let rows: Vec<&str> = text.lines().map(str::trim_end).filter(|l| !l.is_empty()).collect();
+        if rows.len() > height {
+            return Err(format!("{} rows drawn but the board is {height} tall", rows.len()));
+        }
+        let mut board = Self::new(width, height);
+        let top = height - rows.len();
+        for (i, row) in rows.iter().enumerate() {
+            let chars: Vec<char> = row.chars().collect();
+            if chars.len() != width {
+                return Err(format!("row {} is {} wide, expected {width}", i + 1, chars.len()));
+            }
+            for (x, ch) in chars.into_iter().enumerate() {
+                let cell = match ch {
+                    '.' => Cell::Empty,
+                    '#' => Cell::Garbage,
+                    '?' => Cell::Infected,
+                    other => Cell::Filled(other, '░'),
+                };
+                board.set(x, top + i, cell);
+            }
         }
+        Ok(board)
+    }
+
+    pub fn to_ascii(&self) -> Vec<String> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| match self.get(x, y) {
+                        Cell::Empty => '.',
+                        Cell::Garbage => '#',
+                        Cell::Infected => '?',
+                        Cell::Filled(left, _) => left,
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
     fn idx(&self, x: usize, y: usize) -> usize {
@@ -28,8 +83,85 @@ impl Board {
         self.cells[self.idx(x, y)]
     }
 
+    pub fn origin(&self, x: usize, y: usize) -> Option<u64> {
+        self.origins[self.idx(x, y)]
+    }
+
     pub fn set(&mut self, x: usize, y: usize, value: Cell) {
+        self.set_with_origin(x, y, value, None);
+    }
+
+    pub fn set_with_origin(&mut self, x: usize, y: usize, value: Cell, origin: Option<u64>) {
         let idx = self.idx(x, y);
         self.cells[idx] = value;
+        self.origins[idx] = origin;
+    }
+
+    // Rows from the resting edge up to and including the highest filled
+    // cell, measured from the top with `toward_top`; 0 for an empty board.
+    pub fn stack_height(&self, toward_top: bool) -> usize {
+        let filled = |y: &usize| (0..self.width).any(|x| self.get(x, *y).is_filled());
+        if toward_top {
+            (0..self.height).rev().find(filled).map_or(0, |y| y + 1)
+        } else {
+            (0..self.height).find(filled).map_or(0, |y| self.height - y)
+        }
+    }
+
+    // Drop the given rows and close the gap: the rows above fall down, or with
+    // `toward_top` the rows below move up.
+    pub fn remove_rows(&mut self, rows: &[usize], toward_top: bool) {
+        let w = self.width;
+        let kept: Vec<usize> = (0..self.height).filter(|y| !rows.contains(y)).collect();
+        let gap = (self.height - kept.len()) * w;
+        let mut cells = Vec::with_capacity(self.cells.len());
+        let mut origins = Vec::with_capacity(self.origins.len());
+        if !toward_top {
+            cells.resize(gap, Cell::Empty);
+            origins.resize(gap, None);
+        }
+        for y in kept {
+            cells.extend_from_slice(&self.cells[y * w..(y + 1) * w]);
+            origins.extend_from_slice(&self.origins[y * w..(y + 1) * w]);
+        }
+        cells.resize(self.cells.len(), Cell::Empty);
+        origins.resize(self.origins.len(), None);
+        self.cells = cells;
+        self.origins = origins;
+    }
+
+    // Push a row in at the bottom edge (or the top with `at_top`), shifting
+    // every other row one step away; the row at the far edge is lost.
+    pub fn push_row(&mut self, row: &[Cell], at_top: bool) {
+        let w = self.width;
+        let n = self.cells.len();
+        if at_top {
+            self.cells.truncate(n - w);
+            self.origins.truncate(n - w);
+            self.cells.splice(0..0, row.iter().copied());
+            self.origins.splice(0..0, std::iter::repeat_n(None, w));
+        } else {
+            self.cells.drain(..w);
+            self.origins.drain(..w);
+            self.cells.extend_from_slice(row);
+            self.origins.resize(n, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_height_measures_from_the_side_the_stack_rests_on() {
+        let board = |rows: &str| Board::from_ascii(rows, 4, 5).unwrap();
+        assert_eq!(board("").stack_height(false), 0);
+        let sample = board("....\n.a..\n....\n##.#");
+        assert_eq!(sample.stack_height(false), 3);
+        assert_eq!(sample.stack_height(true), 5);
+        let hanging = board("..b.\n....\n....\n....\n....");
+        assert_eq!(hanging.stack_height(false), 5);
+        assert_eq!(hanging.stack_height(true), 1);
     }
 }
@@ -1,17 +1,54 @@
+use std::time::Duration;
+
+use log::debug;
 use rand::seq::IteratorRandom;
 use rand::Rng;
 
-use crate::game::Cell;
+use crate::config::{FailureVariety, GravityDir, RewardTable};
+use crate::game::{Cell, Piece, TopOut};
 use crate::{BOMB_CAP, VARIETY_THRESH};
 
 use super::Game;
 
+// Columns a tilted hole can shift per row at the longest failure streaks.
+const MAX_TILT_DRIFT: usize = 3;
+
+// Gravity pause per freeze, and the most that can be banked at once.
+const FREEZE_TIME: Duration = Duration::from_secs(5);
+const FREEZE_CAP: Duration = Duration::from_secs(10);
+// Shuffles per spawned piece; a second one would only re-roll the same queue.
+const SHUFFLE_CAP: u32 = 1;
+
+// What a full variety meter pays out. Bombs are stocked up to `BOMB_CAP`,
+// freezes up to `FREEZE_CAP` and shuffles up to `SHUFFLE_CAP` per piece; a
+// garbage clear takes effect at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerUp {
+    Bomb,
+    GarbageClear,
+    Shuffle,
+    Freeze,
+}
+
+impl PowerUp {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "bomb" => PowerUp::Bomb,
+            "garbage-clear" => PowerUp::GarbageClear,
+            "shuffle" => PowerUp::Shuffle,
+            "freeze" => PowerUp::Freeze,
+            _ => return None,
+        })
+    }
+}
+
 impl Game {
-    pub(super) fn apply_bomb_clear(&mut self) {
+    pub(super) fn apply_bomb_clear(&mut self, bomb: &Piece) {
         let mut to_clear = Vec::new();
-        for (x, y, _) in self.current.cells() {
-            for dy in -1..=1 {
-                for dx in -1..=1 {
+        let r = self.config.bomb_radius;
+        for (x, y, _) in bomb.cells() {
+            for dy in -r..=r {
+                for dx in -r..=r {
                     let nx = x + dx;
                     let ny = y + dy;
                     if nx >= 0 && ny >= 0 {
@@ -31,36 +68,56 @@ impl Game {
     }
 
     pub(super) fn apply_garbage_row(&mut self) {
-        let mut rng = rand::thread_rng();
-        let hole = rng.gen_range(0..self.board.width);
-        let mut new_cells = vec![Cell::Empty; self.board.width * self.board.height];
-        // shift everything up by one row
-        for y in 1..self.board.height {
-            for x in 0..self.board.width {
-                let src = self.board.get(x, y);
-                let dst_idx = (y - 1) * self.board.width + x;
-                new_cells[dst_idx] = src;
-            }
+        let hole = self.garbage_hole();
+        let (width, height) = (self.board.width, self.board.height);
+        // Garbage enters on the side the stack rests on and pushes it toward
+        // the spawn edge.
+        let up = self.config.gravity == GravityDir::Up;
+        let spawn_y = if up { height - 1 } else { 0 };
+        let row: Vec<Cell> = (0..width)
+            .map(|x| if x == hole { Cell::Empty } else { Cell::Garbage })
+            .collect();
+        // Filled cells on the spawn row get pushed out through the ceiling.
+        let overflow = (0..width).any(|x| self.board.get(x, spawn_y).is_filled());
+        self.board.push_row(&row, up);
+        debug!("garbage row with hole at {hole} (overflow: {overflow})");
+        // The row is in play now; the UI just draws the stack rising into place.
+        if self.config.garbage_rise_frames > 0 {
+            self.garbage_rise_rows = (self.garbage_rise_rows + 1).min(height);
+            self.garbage_rise_frames = self.config.garbage_rise_frames;
         }
-        // bottom row with garbage except hole
-        for x in 0..self.board.width {
-            let idx = (self.board.height - 1) * self.board.width + x;
-            if x == hole {
-                new_cells[idx] = Cell::Empty;
-            } else {
-                new_cells[idx] = Cell::Filled('#', '░');
-            }
-        }
-        // If top row had filled cells, game over.
-        let overflow = (0..self.board.width).any(|x| matches!(self.board.get(x, 0), Cell::Filled(_, _)));
-        self.board.cells = new_cells;
         if overflow {
-            self.game_over = true;
+            self.check_top_out(TopOut::LockOut);
+        }
+    }
+
+    // Random, unless the board is tilted: then each row's hole steps further
+    // along from the previous one the longer the failure streak runs.
+    fn garbage_hole(&mut self) -> usize {
+        let width = self.board.width;
+        let drift = if self.config.tilt {
+            (self.fail_streak.saturating_sub(1) as usize).min(MAX_TILT_DRIFT)
+        } else {
+            0
+        };
+        let hole = match self.last_garbage_hole {
+            Some(prev) if drift > 0 => (prev + drift) % width,
+            _ => self.rng.gen_range(0..width),
+        };
+        self.last_garbage_hole = Some(hole);
+        hole
+    }
+
+    pub(super) fn apply_garbage_rows(&mut self, rows: u64) {
+        for _ in 0..rows {
+            if self.game_over {
+                break;
+            }
+            self.apply_garbage_row();
         }
     }
 
     pub(super) fn apply_infection(&mut self) {
-        let mut rng = rand::thread_rng();
         let mut filled: Vec<(usize, usize)> = Vec::new();
         for y in 0..self.board.height {
             for x in 0..self.board.width {
@@ -69,9 +126,11 @@ impl Game {
                 }
             }
         }
-        let count = filled.len().min(5);
-        for &(x, y) in filled.iter().choose_multiple(&mut rng, count) {
-            self.board.set(x, y, Cell::Filled('?', '░'));
+        let infected = self.board.cells.iter().filter(|c| **c == Cell::Infected).count();
+        let room = self.config.infection_cap.map_or(usize::MAX, |cap| cap.saturating_sub(infected));
+        let count = filled.len().min(self.config.infection_per_failure).min(room);
+        for &(x, y) in filled.iter().choose_multiple(&mut self.rng, count) {
+            self.board.set(x, y, Cell::Infected);
         }
     }
 
@@ -90,16 +149,290 @@ impl Game {
         } else {
             10 + 3 * (self.variety_streak.min(10))
         };
+        if let Some(window) = self.config.blitz_window {
+            let distinct = self.blitz.record(self.elapsed, identity, window);
+            if !same_as_last && distinct >= self.config.blitz_min {
+                debug!("blitz: {distinct} distinct commands within {window:?}");
+                variety_points += self.config.blitz_bonus;
+            }
+        }
 
-        if exit_code != 0 {
-            variety_points /= 2;
+        if self.config.is_failure(exit_code) {
+            variety_points = match self.config.failure_variety {
+                FailureVariety::Full => variety_points,
+                FailureVariety::Half => variety_points / 2,
+                FailureVariety::Zero => 0,
+            };
         }
 
-        self.variety_meter += variety_points;
+        self.add_variety(variety_points);
+        debug!(
+            "variety `{identity}` exit {exit_code}: +{variety_points}, meter {}, streak {}, bombs {}",
+            self.variety_meter, self.variety_streak, self.bombs
+        );
+    }
 
+    // Fill the meter, paying out one power-up per threshold crossed. Ironman
+    // still cycles the meter for stats but never pays out. A payout at its
+    // cap holds the meter full instead of being spent for nothing.
+    pub(super) fn add_variety(&mut self, points: i32) {
+        self.variety_meter += points;
         while self.variety_meter >= VARIETY_THRESH {
+            if self.config.ironman {
+                self.variety_meter -= VARIETY_THRESH;
+                continue;
+            }
+            let power_up = self.pick_power_up();
+            if self.at_cap(power_up) {
+                debug!("power-up {power_up:?} at its cap, meter held full");
+                self.variety_meter = VARIETY_THRESH;
+                break;
+            }
             self.variety_meter -= VARIETY_THRESH;
-            self.bombs = (self.bombs + 1).min(BOMB_CAP);
+            self.grant_power_up(power_up);
+        }
+    }
+
+    fn at_cap(&self, power_up: PowerUp) -> bool {
+        match power_up {
+            PowerUp::Bomb => self.bombs >= BOMB_CAP,
+            PowerUp::GarbageClear => false,
+            PowerUp::Shuffle => self.shuffles >= SHUFFLE_CAP,
+            PowerUp::Freeze => self.freeze_left >= FREEZE_CAP,
+        }
+    }
+
+    // The next entry of a configured reward table. Without one: usually a
+    // bomb, and with garbage on the board sometimes a garbage clear.
+    fn pick_power_up(&mut self) -> PowerUp {
+        match &self.config.rewards {
+            Some(RewardTable::Rotation(order)) => {
+                let power_up = order[self.reward_turn % order.len()];
+                self.reward_turn += 1;
+                return power_up;
+            }
+            Some(RewardTable::Weighted(weights)) => {
+                let total: u32 = weights.iter().map(|&(_, w)| w).sum();
+                let mut roll = self.rng.gen_range(0..total);
+                for &(power_up, weight) in weights {
+                    if roll < weight {
+                        return power_up;
+                    }
+                    roll -= weight;
+                }
+            }
+            None => {}
+        }
+        let odds = self.config.garbage_clear_odds;
+        if odds > 0 && !self.garbage_rows().is_empty() && self.rng.gen_ratio(1, odds) {
+            PowerUp::GarbageClear
+        } else {
+            PowerUp::Bomb
+        }
+    }
+
+    pub(super) fn grant_power_up(&mut self, power_up: PowerUp) {
+        match power_up {
+            PowerUp::Bomb => self.bombs = (self.bombs + 1).min(BOMB_CAP),
+            PowerUp::GarbageClear => self.apply_garbage_clear(),
+            PowerUp::Shuffle => {
+                self.shuffles += 1;
+                self.reshuffle_queue();
+            }
+            PowerUp::Freeze => self.freeze_left = (self.freeze_left + FREEZE_TIME).min(FREEZE_CAP),
         }
+        debug!("power-up {power_up:?}");
+    }
+
+    // Rows holding any garbage, including ones a bomb or the player has
+    // partly reworked.
+    pub(super) fn garbage_rows(&self) -> Vec<usize> {
+        (0..self.board.height)
+            .filter(|&y| (0..self.board.width).any(|x| self.board.get(x, y) == Cell::Garbage))
+            .collect()
+    }
+
+    // Remove every garbage row and close the gaps like a line clear, but
+    // unscored. A clear already flashing resolves first so its rows still match.
+    pub(super) fn apply_garbage_clear(&mut self) {
+        if !self.pending_clear.is_empty() {
+            self.clear_flash_frames = 0;
+            self.perform_pending_clear();
+        }
+        let rows = self.garbage_rows();
+        debug!("garbage clear: rows {rows:?}");
+        self.clear_rows(&rows);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::game::{Board, Shape};
+    use crate::testing::*;
+    use crate::{CommandEvent, BOARD_H, BOARD_W};
+
+    #[test]
+    fn radius_two_bomb_clears_five_by_five_around_each_cell() {
+        let mut game = game_with(|c| c.bomb_radius = 2);
+        game.board = Board::from_ascii(&"##########\n".repeat(BOARD_H), BOARD_W, BOARD_H).unwrap();
+        let mut bomb = Piece::with_payload(Shape::O, vec!['▓'; 8]);
+        bomb.y = 10;
+        let centers = bomb.cells();
+        game.apply_bomb_clear(&bomb);
+        for y in 0..BOARD_H as i32 {
+            for x in 0..BOARD_W as i32 {
+                let in_blast = centers.iter().any(|&(cx, cy, _)| (x - cx).abs() <= 2 && (y - cy).abs() <= 2);
+                let expected = if in_blast { Cell::Empty } else { Cell::Garbage };
+                assert_eq!(game.board.get(x as usize, y as usize), expected, "cell ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn ironman_crossing_the_threshold_grants_no_bombs() {
+        let mut ironman = game_with(|c| c.ironman = true);
+        let mut normal = game(Config::default());
+        for game in [&mut ironman, &mut normal] {
+            game.add_variety(VARIETY_THRESH + 10);
+            assert_eq!(game.variety_meter, 10);
+        }
+        assert_eq!(ironman.bombs, 0);
+        assert_eq!(normal.bombs, 1);
+    }
+
+    #[test]
+    fn tilted_garbage_holes_drift_with_the_streak() {
+        let mut game = game_with(|c| c.tilt = true);
+        game.fail_streak = 3;
+        let bottom_hole = |game: &Game| (0..BOARD_W).find(|&x| game.board.get(x, BOARD_H - 1) == Cell::Empty).unwrap();
+        game.apply_garbage_row();
+        let mut hole = bottom_hole(&game);
+        for _ in 0..4 {
+            game.apply_garbage_row();
+            let next = bottom_hole(&game);
+            assert_eq!(next, (hole + 2) % BOARD_W);
+            hole = next;
+        }
+    }
+
+    #[test]
+    fn failure_variety_rules_scale_a_failed_varied_command() {
+        for (rule, meter) in [(FailureVariety::Full, 13), (FailureVariety::Half, 6), (FailureVariety::Zero, 0)] {
+            let mut game = game_with(|c| c.failure_variety = rule);
+            game.last_cmd_identity = Some("ls".to_string());
+            game.apply_variety("cargo", 1);
+            assert_eq!(game.variety_meter, meter, "{rule:?}");
+        }
+    }
+
+    #[test]
+    fn infection_stops_at_the_cap() {
+        let mut game = game_with(|c| {
+            c.infection_per_failure = 2;
+            c.infection_cap = Some(4);
+        });
+        game.board = Board::from_ascii(&"aaaaaaaaa.\n".repeat(4), BOARD_W, BOARD_H).unwrap();
+        let infected = |game: &Game| game.board.cells.iter().filter(|&&c| c == Cell::Infected).count();
+        game.apply_infection();
+        assert_eq!(infected(&game), 2);
+        game.apply_infection();
+        assert_eq!(infected(&game), 4);
+        game.apply_infection();
+        assert_eq!(infected(&game), 4);
+    }
+
+    #[test]
+    fn garbage_clear_removes_garbage_rows_and_drops_the_rest() {
+        let mut game = game(Config::default());
+        game.board = Board::from_ascii("aa........\n#########.\nbb........\n##.#######", BOARD_W, BOARD_H).unwrap();
+        game.grant_power_up(PowerUp::GarbageClear);
+        let expected = Board::from_ascii("aa........\nbb........", BOARD_W, BOARD_H).unwrap();
+        assert_eq!(game.board.to_ascii(), expected.to_ascii());
+        assert!(game.garbage_rows().is_empty());
+        assert_eq!(game.lines_cleared, 0);
+    }
+
+    #[test]
+    fn configured_bomb_shape_sets_the_blast_centers() {
+        let mut game = game_with(|c| c.bomb_shape = Shape::I);
+        game.bombs = 1;
+        game.spawn_next();
+        assert!(game.current_is_bomb);
+        let bomb = game.current.clone().unwrap();
+        assert_eq!(bomb.shape, Shape::I);
+        let cells = bomb.cells();
+        assert!(cells.iter().all(|&(_, y, _)| y == 1));
+        game.board = Board::from_ascii(&"##########\n".repeat(BOARD_H), BOARD_W, BOARD_H).unwrap();
+        game.apply_bomb_clear(&bomb);
+        let cleared = game.board.cells.iter().filter(|&&c| c == Cell::Empty).count();
+        // A 4-wide line on row 1 blasts 6 columns of rows 0 to 2.
+        assert_eq!(cleared, 6 * 3);
+    }
+
+    #[test]
+    fn reward_rotation_grants_power_ups_in_order() {
+        let order = vec![PowerUp::Freeze, PowerUp::Bomb, PowerUp::Shuffle];
+        let mut game = game_with(|c| c.rewards = Some(RewardTable::Rotation(order.clone())));
+        let picks: Vec<PowerUp> = (0..6).map(|_| game.pick_power_up()).collect();
+        assert_eq!(picks, [order.clone(), order].concat());
+
+        game.reward_turn = 0;
+        game.add_variety(VARIETY_THRESH);
+        assert_eq!(game.freeze_left, FREEZE_TIME);
+        assert_eq!(game.bombs, 0);
+        game.add_variety(VARIETY_THRESH);
+        assert_eq!(game.bombs, 1);
+    }
+
+    #[test]
+    fn a_capped_power_up_holds_the_meter_full() {
+        let mut shuffled = game_with(|c| c.rewards = Some(RewardTable::Rotation(vec![PowerUp::Shuffle])));
+        shuffled.add_variety(VARIETY_THRESH);
+        assert_eq!((shuffled.shuffles, shuffled.variety_meter), (1, 0));
+        shuffled.add_variety(VARIETY_THRESH + 30);
+        assert_eq!((shuffled.shuffles, shuffled.variety_meter), (1, VARIETY_THRESH));
+
+        let mut stocked = game(Config::default());
+        stocked.bombs = BOMB_CAP;
+        stocked.add_variety(VARIETY_THRESH);
+        assert_eq!((stocked.bombs, stocked.variety_meter), (BOMB_CAP, VARIETY_THRESH));
+        stocked.bombs -= 1;
+        stocked.add_variety(0);
+        assert_eq!((stocked.bombs, stocked.variety_meter), (BOMB_CAP, 0));
+    }
+
+    #[test]
+    fn rising_garbage_state_clears_after_the_configured_frames() {
+        let mut game = game_with(|c| c.garbage_rise_frames = 3);
+        game.apply_garbage_row();
+        assert_eq!((game.garbage_rise_rows, game.garbage_rise_frames), (1, 3));
+        assert_eq!(game.garbage_rise_offset(), 1);
+        for _ in 0..3 {
+            game.process_effects();
+        }
+        assert_eq!((game.garbage_rise_rows, game.garbage_rise_frames), (0, 0));
+        assert_eq!(game.garbage_rise_offset(), 0);
+    }
+
+    #[test]
+    fn distinct_commands_in_a_burst_earn_the_blitz_bonus() {
+        let config = config_with(|c| c.blitz_window = Some(Duration::from_secs(10)));
+        let meter_after = |commands: [&str; 3], gap: Duration| {
+            let mut game = game(config.clone());
+            for (id, command) in (1..).zip(commands) {
+                game.advance_clock(gap);
+                game.handle_command_event(CommandEvent::Start { id, command: command.to_string() });
+                game.handle_command_event(CommandEvent::End { id, exit_code: 0, stderr_lines: None, duration_ms: None });
+            }
+            game.variety_meter
+        };
+        let (burst, spread) = (Duration::from_secs(1), Duration::from_secs(30));
+        let distinct = ["make", "cargo build", "git status"];
+        assert_eq!(meter_after(distinct, burst), meter_after(distinct, spread) + config.blitz_bonus);
+        // Subcommands of one program share its identity.
+        let subcommands = ["cargo build", "cargo test", "cargo check"];
+        assert_eq!(meter_after(subcommands, burst), meter_after(subcommands, spread));
     }
 }
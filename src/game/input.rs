@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use log::{debug, warn};
+
+use super::report::{write_report, RECENT_INPUTS};
+use super::{CommandEvent, Game, Shape};
+use crate::commands::chunk_to_payload;
+use crate::CHUNK_SIZE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Left,
+    Right,
+    SoftDrop,
+    Rotate,
+    HardDrop,
+    Restart,
+    Priority,
+}
+
+// One step of game input. The app loop, replays and scripts all drive the
+// game through these so a recorded session plays back identically.
+#[derive(Clone, Debug)]
+pub enum Input {
+    Command(CommandEvent),
+    Action(Action),
+    Gravity,
+    // One render pass: advance the clock by this much, then run effect timers.
+    Frame(Duration),
+    // Debug-only: replace the falling piece, see `Game::inject_piece`.
+    Inject { shape: Shape, col: i32, payload: Vec<char> },
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::Left,
+        Action::Right,
+        Action::SoftDrop,
+        Action::Rotate,
+        Action::HardDrop,
+        Action::Restart,
+        Action::Priority,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::SoftDrop => "soft-drop",
+            Action::Rotate => "rotate",
+            Action::HardDrop => "hard-drop",
+            Action::Restart => "restart",
+            Action::Priority => "priority",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+impl Input {
+    // `<shape letter> <column> [text]`, as the INJECT debug command, script
+    // step and log entry spell it. Without text the piece shows its letter.
+    pub fn parse_inject(args: &str) -> Result<Input, String> {
+        let mut parts = args.splitn(3, ' ');
+        let letter = parts.next().unwrap_or("");
+        let shape = letter
+            .chars()
+            .next()
+            .filter(|_| letter.chars().count() == 1)
+            .and_then(Shape::from_letter)
+            .ok_or_else(|| format!("unknown shape `{letter}`"))?;
+        let col = parts.next().unwrap_or("");
+        let col = col.parse().map_err(|_| format!("bad column `{col}`"))?;
+        let text = match parts.next() {
+            Some(text) if !text.is_empty() => text.to_string(),
+            _ => shape.letter().to_string().repeat(CHUNK_SIZE),
+        };
+        Ok(Input::Inject {
+            shape,
+            col,
+            payload: chunk_to_payload(&text),
+        })
+    }
+}
+
+impl Game {
+    pub fn apply_action(&mut self, action: Action) {
+        let moves_piece = !matches!(action, Action::Restart | Action::Priority);
+        // The ending plays itself; only a restart cuts it short.
+        if self.ending && action != Action::Restart {
+            return;
+        }
+        if self.entry_delay > 0 && moves_piece {
+            self.buffered_actions.push(action);
+            return;
+        }
+        match action {
+            Action::Left => {
+                if self.move_current(-1, 0) {
+                    self.reset_lock_delay();
+                }
+            }
+            Action::Right => {
+                if self.move_current(1, 0) {
+                    self.reset_lock_delay();
+                }
+            }
+            Action::SoftDrop => {
+                let _ = self.soft_drop();
+            }
+            Action::Rotate => {
+                if self.rotate_current() {
+                    self.reset_lock_delay();
+                }
+            }
+            Action::HardDrop => self.hard_drop(),
+            Action::Restart => {
+                // A practice drill can be started over at any point.
+                if self.game_over || self.config.scenario.is_some() {
+                    self.reset();
+                }
+            }
+            Action::Priority => self.cycle_priority(),
+        }
+    }
+
+    pub fn apply_input(&mut self, input: Input) {
+        let was_over = self.game_over;
+        if !matches!(input, Input::Frame(_)) {
+            if self.recent_inputs.len() == RECENT_INPUTS {
+                self.recent_inputs.pop_front();
+            }
+            self.recent_inputs.push_back((self.elapsed, input.clone()));
+        }
+        match input {
+            Input::Command(ev) => self.handle_command_event(ev),
+            Input::Action(action) => self.apply_action(action),
+            Input::Gravity => self.tick_gravity(),
+            Input::Frame(dt) => {
+                self.advance_clock(dt);
+                self.process_effects();
+            }
+            Input::Inject { shape, col, payload } => {
+                let _ = self.inject_piece(shape, col, payload);
+            }
+        }
+        if !was_over && self.game_over {
+            self.save_game_over_report();
+        }
+    }
+
+    fn save_game_over_report(&self) {
+        let Some(dir) = &self.config.game_over_dir else {
+            return;
+        };
+        match write_report(self, dir) {
+            Ok(path) => debug!("game over: saved report to {}", path.display()),
+            Err(e) => warn!("game over: cannot save report in {}: {e}", dir.display()),
+        }
+    }
+}
@@ -1,8 +1,19 @@
+pub mod blitz;
 pub mod board;
 pub mod effects;
+pub mod input;
+pub mod pace;
 pub mod piece;
+pub mod report;
+pub mod scenario;
+pub mod snapshot;
+pub mod success;
 pub mod state;
 
 pub use board::{Board, Cell};
-pub use piece::{random_shape, Piece, Shape};
-pub use state::{CommandEvent, Game};
+pub use effects::PowerUp;
+pub use input::{Action, Input};
+pub use scenario::{load_scenario, Scenario};
+pub use piece::{pick_shape, random_shape, Piece, Shape, ROTATIONS};
+pub use snapshot::{decode_snapshot, encode_snapshot, Snapshot};
+pub use state::{CommandEvent, Game, TopOut};
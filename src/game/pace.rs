@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// How far back the falling-behind check looks, in game time.
+const PACE_WINDOW: Duration = Duration::from_secs(15);
+// Net pieces the backlog may grow by within the window before we warn; a
+// single command queues a few pieces at once, which shouldn't trip it.
+const BEHIND_SLACK: u32 = 4;
+
+// Pieces queued versus pieces locked over a sliding window of game time.
+#[derive(Default)]
+pub struct PaceTracker {
+    queued: VecDeque<(Duration, u32)>,
+    locks: VecDeque<Duration>,
+}
+
+impl PaceTracker {
+    pub fn record_queued(&mut self, at: Duration, pieces: u32) {
+        if pieces > 0 {
+            self.queued.push_back((at, pieces));
+        }
+    }
+
+    pub fn record_lock(&mut self, at: Duration) {
+        self.locks.push_back(at);
+    }
+
+    // Pieces added minus pieces locked within the window ending at `now`.
+    pub fn net(&mut self, now: Duration) -> i64 {
+        let start = now.saturating_sub(PACE_WINDOW);
+        while self.queued.front().is_some_and(|&(at, _)| at < start) {
+            self.queued.pop_front();
+        }
+        while self.locks.front().is_some_and(|&at| at < start) {
+            self.locks.pop_front();
+        }
+        let queued: i64 = self.queued.iter().map(|&(_, n)| i64::from(n)).sum();
+        queued - self.locks.len() as i64
+    }
+
+    pub fn falling_behind(&mut self, now: Duration) -> bool {
+        self.net(now) > i64::from(BEHIND_SLACK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn falling_behind_once_the_backlog_outgrows_the_slack() {
+        let mut pace = PaceTracker::default();
+        pace.record_queued(secs(1), 3);
+        pace.record_queued(secs(2), 3);
+        assert!(pace.falling_behind(secs(3)));
+        pace.record_lock(secs(3));
+        pace.record_lock(secs(4));
+        assert_eq!(pace.net(secs(4)), 4);
+        assert!(!pace.falling_behind(secs(4)));
+        // Old history slides out of the window.
+        pace.record_queued(secs(20), 5);
+        assert_eq!(pace.net(secs(20)), 5);
+        assert!(pace.falling_behind(secs(20)));
+        assert!(!pace.falling_behind(secs(40)));
+    }
+}
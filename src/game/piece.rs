@@ -1,7 +1,7 @@
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Shape {
     I,
     O,
@@ -12,6 +12,43 @@ pub enum Shape {
     L,
 }
 
+impl Shape {
+    pub const ALL: [Shape; 7] = [
+        Shape::I,
+        Shape::O,
+        Shape::T,
+        Shape::S,
+        Shape::Z,
+        Shape::J,
+        Shape::L,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_letter(letter: char) -> Option<Shape> {
+        Shape::ALL
+            .into_iter()
+            .find(|shape| shape.letter() == letter.to_ascii_uppercase())
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            Shape::I => 'I',
+            Shape::O => 'O',
+            Shape::T => 'T',
+            Shape::S => 'S',
+            Shape::Z => 'Z',
+            Shape::J => 'J',
+            Shape::L => 'L',
+        }
+    }
+}
+
+// Every shape has four rotation states (O's are identical).
+pub const ROTATIONS: u8 = 4;
+
 #[derive(Clone)]
 pub struct Piece {
     pub shape: Shape,
@@ -63,9 +100,14 @@ impl Piece {
             .collect()
     }
 
+    // One clockwise turn. The O piece is the same in every orientation, so it
+    // comes back unchanged, rotation index included.
     pub fn rotated(&self) -> Self {
         let mut next = self.clone();
-        next.rotation = (next.rotation + 1) % 4;
+        if self.shape == Shape::O {
+            return next;
+        }
+        next.rotation = (next.rotation + 1) % ROTATIONS;
         next
     }
 
@@ -78,17 +120,11 @@ impl Piece {
 }
 
 pub fn random_shape() -> Shape {
-    let shapes = [
-        Shape::I,
-        Shape::O,
-        Shape::T,
-        Shape::S,
-        Shape::Z,
-        Shape::J,
-        Shape::L,
-    ];
-    let mut rng = thread_rng();
-    *shapes.choose(&mut rng).unwrap_or(&Shape::I)
+    pick_shape(&mut thread_rng())
+}
+
+pub fn pick_shape(rng: &mut impl Rng) -> Shape {
+    *Shape::ALL.choose(rng).unwrap_or(&Shape::I)
 }
 
 pub fn shape_offsets(shape: Shape, rotation: u8) -> &'static [(i32, i32)] {
@@ -135,7 +171,7 @@ pub fn shape_offsets(shape: Shape, rotation: u8) -> &'static [(i32, i32)] {
         [(0, 0), (1, 0), (1, 1), (1, 2)],
     ];
 
-    let r = (rotation % 4) as usize;
+    let r = (rotation % ROTATIONS) as usize;
     match shape {
         Shape::I => &I[r],
         Shape::O => &O[r],
@@ -0,0 +1,67 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::{encode_snapshot, Game};
+use crate::replay::log::{escape, format_input};
+
+// Inputs kept for the game-over report. Clock frames are left out; the game
+// time on each line shows how far apart the others were.
+pub const RECENT_INPUTS: usize = 100;
+
+// Game-over report, for attaching to bug reports:
+//
+//   # waitris game-over report
+//   SEED <u64>
+//   SET <NAME>=<value>         (the recorded settings, as in an event log)
+//   SNAPSHOT <board snapshot, loadable with STACK_SNAPSHOT>
+//   <game ms> <input>          (last RECENT_INPUTS inputs, event log syntax)
+pub fn write_report(game: &Game, dir: &Path) -> io::Result<PathBuf> {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let path = dir.join(format!("waitris-gameover-{stamp}.txt"));
+    let mut text = String::from("# waitris game-over report\n");
+    text.push_str(&format!("SEED {}\n", game.seed));
+    for (name, value) in &game.config.settings {
+        text.push_str(&format!("SET {name}={}\n", escape(value)));
+    }
+    text.push_str(&format!("SNAPSHOT {}\n", encode_snapshot(&game.snapshot())));
+    for (at, input) in &game.recent_inputs {
+        text.push_str(&format!("{} {}\n", at.as_millis(), format_input(input)));
+    }
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::game::{decode_snapshot, Board, Input};
+    use crate::testing::game_with;
+    use crate::{CommandEvent, BOARD_H, BOARD_W};
+
+    #[test]
+    fn game_over_writes_a_report_with_the_final_board() {
+        let dir = env::temp_dir().join(format!("stack-game-report-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut game = game_with(|c| c.game_over_dir = Some(dir.clone()));
+        game.board = Board::from_ascii(&"#########.\n".repeat(BOARD_H), BOARD_W, BOARD_H).unwrap();
+        game.apply_input(Input::Command(CommandEvent::Start {
+            id: 1,
+            command: "cargo build".to_string(),
+        }));
+        assert!(game.game_over);
+
+        let reports: Vec<_> = fs::read_dir(&dir).unwrap().flatten().collect();
+        assert_eq!(reports.len(), 1);
+        let text = fs::read_to_string(reports[0].path()).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(text.contains(&format!("SEED {}\n", game.seed)));
+        assert!(text.contains("START 1 cargo build"));
+        let snapshot = text.lines().find_map(|l| l.strip_prefix("SNAPSHOT ")).unwrap();
+        assert_eq!(decode_snapshot(snapshot).unwrap().board, game.board);
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::Path;
+
+use super::{Board, Shape};
+use crate::{BOARD_H, BOARD_W};
+
+// A practice drill, read from `<dir>/<name>.txt`:
+//
+//   // optional comments
+//   PIECES T I O          (the pieces dealt, in order)
+//   ..........
+//   ##.#######            (the board, bottom-aligned; see `Board::from_ascii`)
+//
+// Comments take `//` since `#` draws garbage. Command events are ignored
+// while one is loaded, and restart puts the board and pieces back as written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scenario {
+    pub name: String,
+    pub board: Board,
+    pub pieces: Vec<Shape>,
+}
+
+pub fn load_scenario(dir: &Path, name: &str) -> Result<Scenario, String> {
+    let path = dir.join(format!("{name}.txt"));
+    let text = fs::read_to_string(&path).map_err(|e| format!("cannot read scenario {}: {e}", path.display()))?;
+    parse_scenario(name, &text).map_err(|e| format!("scenario {}: {e}", path.display()))
+}
+
+pub fn parse_scenario(name: &str, text: &str) -> Result<Scenario, String> {
+    let mut pieces = Vec::new();
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if let Some(list) = line.strip_prefix("PIECES ") {
+            for word in list.split_whitespace() {
+                let shape = word
+                    .chars()
+                    .next()
+                    .filter(|_| word.len() == 1)
+                    .and_then(Shape::from_letter)
+                    .ok_or_else(|| format!("unknown piece `{word}`"))?;
+                pieces.push(shape);
+            }
+        } else {
+            rows.push(line);
+        }
+    }
+    if pieces.is_empty() {
+        return Err("no PIECES line".to_string());
+    }
+    let board = Board::from_ascii(&rows.join("\n"), BOARD_W, BOARD_H)?;
+    Ok(Scenario {
+        name: name.to_string(),
+        board,
+        pieces,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Action, Cell};
+    use crate::testing::game_with;
+
+    const DRILL: &str = "// T-spin double
+PIECES T I O
+##.#######
+#...######
+##.#######
+";
+
+    #[test]
+    fn rows_starting_with_a_hash_are_board_rows() {
+        let scenario = parse_scenario("tsd", DRILL).unwrap();
+        assert_eq!(scenario.pieces, [Shape::T, Shape::I, Shape::O]);
+        let rows = scenario.board.to_ascii();
+        assert_eq!(rows[BOARD_H - 3..], ["##.#######", "#...######", "##.#######"]);
+        assert_eq!(scenario.board.get(0, BOARD_H - 1), Cell::Garbage);
+        assert!(parse_scenario("none", "// no pieces\n..........\n").is_err());
+    }
+
+    #[test]
+    fn practice_starts_from_the_drill_and_restart_restores_it() {
+        let scenario = parse_scenario("tsd", DRILL).unwrap();
+        let mut game = game_with(|c| c.scenario = Some(scenario.clone()));
+        assert_eq!(game.board, scenario.board);
+        assert_eq!(game.current.as_ref().map(|p| p.shape), Some(Shape::T));
+        game.apply_action(Action::HardDrop);
+        assert_ne!(game.board, scenario.board);
+        game.apply_action(Action::Restart);
+        assert_eq!(game.board, scenario.board);
+        assert_eq!(game.current.as_ref().map(|p| p.shape), Some(Shape::T));
+    }
+}
@@ -0,0 +1,311 @@
+use crate::config::{BOARD_H, BOARD_W};
+use crate::game::{Board, Cell, Piece, Shape};
+
+use super::Game;
+
+// Compact, copy-pasteable board state for bug reports. A snapshot is a
+// version byte, the board dimensions, run-length encoded cells and the
+// falling piece, all base64 encoded. New cell kinds get new tags; existing
+// tags never change meaning within a version.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+const TAG_EMPTY_RUN: u8 = 0;
+const TAG_FILLED: u8 = 1;
+const TAG_INFECTED: u8 = 2;
+const TAG_GARBAGE: u8 = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub board: Board,
+    pub current: Option<SnapshotPiece>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotPiece {
+    pub shape: Shape,
+    pub rotation: u8,
+    pub x: i32,
+    pub y: i32,
+    pub payload: Vec<char>,
+    pub is_bomb: bool,
+}
+
+impl Game {
+    pub fn snapshot(&self) -> Snapshot {
+        let current = self.current.as_ref().map(|p| SnapshotPiece {
+            shape: p.shape,
+            rotation: p.rotation,
+            x: p.x,
+            y: p.y,
+            payload: p.payload.clone(),
+            is_bomb: self.current_is_bomb,
+        });
+        Snapshot {
+            board: self.board.clone(),
+            current,
+        }
+    }
+
+    pub fn load_snapshot(&mut self, snap: &Snapshot) {
+        self.board = snap.board.clone();
+        self.pending_clear.clear();
+        match &snap.current {
+            Some(p) => {
+                self.current = Some(Piece {
+                    shape: p.shape,
+                    rotation: p.rotation,
+                    x: p.x,
+                    y: p.y,
+                    payload: p.payload.clone(),
+                });
+                self.current_is_bomb = p.is_bomb;
+            }
+            None => {
+                self.current = None;
+                self.current_is_bomb = false;
+            }
+        }
+    }
+}
+
+pub fn encode_snapshot(snap: &Snapshot) -> String {
+    let mut out = vec![SNAPSHOT_VERSION, snap.board.width as u8, snap.board.height as u8];
+    let mut empties = 0u8;
+    for cell in &snap.board.cells {
+        if let Cell::Empty = cell {
+            if empties == u8::MAX {
+                out.extend([TAG_EMPTY_RUN, empties]);
+                empties = 0;
+            }
+            empties += 1;
+            continue;
+        }
+        if empties > 0 {
+            out.extend([TAG_EMPTY_RUN, empties]);
+            empties = 0;
+        }
+        match cell {
+            Cell::Filled(left, right) => {
+                out.push(TAG_FILLED);
+                push_char(&mut out, *left);
+                push_char(&mut out, *right);
+            }
+            Cell::Infected => out.push(TAG_INFECTED),
+            Cell::Garbage => out.push(TAG_GARBAGE),
+            Cell::Empty => {}
+        }
+    }
+    if empties > 0 {
+        out.extend([TAG_EMPTY_RUN, empties]);
+    }
+
+    match &snap.current {
+        None => out.push(0),
+        Some(p) => {
+            out.push(1);
+            out.push(p.shape.index() as u8);
+            out.push(p.rotation);
+            out.push(p.x as i8 as u8);
+            out.push(p.y as i8 as u8);
+            out.push(p.is_bomb as u8);
+            out.push(p.payload.len() as u8);
+            for ch in &p.payload {
+                push_char(&mut out, *ch);
+            }
+        }
+    }
+    base64_encode(&out)
+}
+
+pub fn decode_snapshot(text: &str) -> Result<Snapshot, String> {
+    let bytes = base64_decode(text.trim())?;
+    let mut r = Reader { bytes: &bytes, pos: 0 };
+    let version = r.byte()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(format!("unsupported snapshot version {version}"));
+    }
+    let width = r.byte()? as usize;
+    let height = r.byte()? as usize;
+    if width != BOARD_W || height != BOARD_H {
+        return Err(format!(
+            "snapshot board is {width}x{height}, expected {BOARD_W}x{BOARD_H}"
+        ));
+    }
+    let mut board = Board::new(width, height);
+    let mut idx = 0;
+    while idx < width * height {
+        match r.byte()? {
+            TAG_EMPTY_RUN => {
+                let run = r.byte()? as usize;
+                if run == 0 || idx + run > width * height {
+                    return Err("snapshot empty run overflows the board".to_string());
+                }
+                idx += run;
+            }
+            TAG_FILLED => {
+                board.cells[idx] = Cell::Filled(r.char()?, r.char()?);
+                idx += 1;
+            }
+            TAG_INFECTED => {
+                board.cells[idx] = Cell::Infected;
+                idx += 1;
+            }
+            TAG_GARBAGE => {
+                board.cells[idx] = Cell::Garbage;
+                idx += 1;
+            }
+            tag => return Err(format!("unknown cell tag {tag} in snapshot")),
+        }
+    }
+
+    let current = match r.byte()? {
+        0 => None,
+        1 => {
+            let shape = *Shape::ALL
+                .get(r.byte()? as usize)
+                .ok_or("unknown shape in snapshot")?;
+            let rotation = r.byte()?;
+            let x = r.byte()? as i8 as i32;
+            let y = r.byte()? as i8 as i32;
+            let is_bomb = r.byte()? != 0;
+            let len = r.byte()? as usize;
+            let payload = (0..len).map(|_| r.char()).collect::<Result<_, _>>()?;
+            Some(SnapshotPiece {
+                shape,
+                rotation,
+                x,
+                y,
+                payload,
+                is_bomb,
+            })
+        }
+        flag => return Err(format!("bad piece flag {flag} in snapshot")),
+    };
+    if r.pos != bytes.len() {
+        return Err("trailing bytes in snapshot".to_string());
+    }
+    Ok(Snapshot { board, current })
+}
+
+fn push_char(out: &mut Vec<u8>, ch: char) {
+    let mut buf = [0u8; 4];
+    out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self.bytes.get(self.pos).ok_or("truncated snapshot")?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn char(&mut self) -> Result<char, String> {
+        let first = self.byte()?;
+        let len = match first {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Err("invalid UTF-8 in snapshot".to_string()),
+        };
+        let start = self.pos - 1;
+        let end = start + len;
+        let slice = self.bytes.get(start..end).ok_or("truncated snapshot")?;
+        self.pos = end;
+        std::str::from_utf8(slice)
+            .ok()
+            .and_then(|s| s.chars().next())
+            .ok_or_else(|| "invalid UTF-8 in snapshot".to_string())
+    }
+}
+
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(B64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for ch in text.bytes() {
+        let val = B64
+            .iter()
+            .position(|b| *b == ch)
+            .ok_or_else(|| format!("invalid base64 character `{}` in snapshot", ch as char))?;
+        acc = (acc << 6) | val as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(rows: &str) -> Board {
+        Board::from_ascii(rows, BOARD_W, BOARD_H).unwrap()
+    }
+
+    #[test]
+    fn snapshots_round_trip_for_several_boards() {
+        let mut infected = board("..........\n##.#######");
+        infected.cells[3] = Cell::Infected;
+        infected.cells[BOARD_W + 5] = Cell::Filled('c', 'é');
+        let boards = [
+            Board::new(BOARD_W, BOARD_H),
+            board("##.#######\n#.########"),
+            infected,
+        ];
+        for board in boards {
+            for current in [
+                None,
+                Some(SnapshotPiece {
+                    shape: Shape::T,
+                    rotation: 2,
+                    x: -1,
+                    y: 3,
+                    payload: "ls".chars().collect(),
+                    is_bomb: true,
+                }),
+            ] {
+                let snap = Snapshot {
+                    board: board.clone(),
+                    current,
+                };
+                assert_eq!(decode_snapshot(&encode_snapshot(&snap)), Ok(snap));
+            }
+        }
+    }
+
+    #[test]
+    fn snapshots_of_another_board_size_are_rejected() {
+        let snap = Snapshot {
+            board: Board::new(BOARD_W + 2, BOARD_H),
+            current: None,
+        };
+        assert!(decode_snapshot(&encode_snapshot(&snap)).is_err());
+    }
+}
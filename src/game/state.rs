@@ -1,16 +1,44 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use log::{debug, trace};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use crate::commands;
-use crate::game::{random_shape, Board, Cell, Piece, Shape};
-use crate::{BOARD_H, BOARD_W, CHUNK_SIZE};
+use crate::game::blitz::BlitzTracker;
+use crate::game::pace::PaceTracker;
+use crate::game::success::SuccessRate;
+use crate::game::{pick_shape, Action, Board, Cell, Input, Piece, Scenario, Shape};
+use crate::config::{Ending, GravityDir, SpawnCollision};
+use crate::{Config, BOARD_H, BOARD_W, CHUNK_SIZE, LINES_PER_LEVEL};
+
+// Most garbage rows one noisy command can add, however much it printed.
+const STDERR_ROW_CAP: u64 = 4;
+// Mixed into the session seed to derive the shape stream.
+const SHAPE_STREAM: u64 = 0x5348_4150_4553;
 
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum CommandEvent {
     Start { id: u64, command: String },
-    End { id: u64, _exit_code: i32 },
+    // `stderr_lines` is only sent by hooks that can count the command's
+    // stderr; `duration_ms` by hooks that time the command.
+    End {
+        id: u64,
+        exit_code: i32,
+        stderr_lines: Option<u64>,
+        duration_ms: Option<u64>,
+    },
+}
+
+pub struct ScoreSnapshot {
+    pub score: u64,
+    pub lines: u64,
+    pub level: u64,
 }
 
+#[derive(Clone)]
 pub(crate) struct QueuedPiece {
     pub run_id: u64,
     pub cycle: u64,
@@ -18,31 +46,48 @@ pub(crate) struct QueuedPiece {
     pub is_bomb: bool,
 }
 
+#[derive(Clone)]
 pub(crate) struct CommandRun {
     pub id: u64,
     pub chunks: Vec<String>,
     pub cycle: u64,
     pub active: bool,
     pub identity: String,
+    pub started_at: Duration,
+    // STARTs folded into this run that haven't ENDed; it stays active until
+    // the last one does.
+    pub open: u32,
 }
 
 impl CommandRun {
-    fn new(id: u64, chunks: Vec<String>, identity: String) -> Self {
+    fn new(id: u64, chunks: Vec<String>, identity: String, started_at: Duration) -> Self {
         Self {
             id,
             chunks,
             cycle: 0,
             active: true,
             identity,
+            started_at,
+            open: 1,
         }
     }
 
-    fn next_cycle_pieces(&mut self) -> (u64, Vec<Piece>) {
+    // Another START of the same command merged in: its pieces cycle afresh
+    // from the new text.
+    fn rejoin(&mut self, chunks: Vec<String>, at: Duration) {
+        self.chunks = chunks;
+        self.cycle = 0;
+        self.open += 1;
+        self.active = true;
+        self.started_at = at;
+    }
+
+    fn next_cycle_pieces(&mut self, rng: &mut StdRng) -> (u64, Vec<Piece>) {
         self.cycle = self.cycle.wrapping_add(1);
         let mut pieces = Vec::new();
         for chunk in &self.chunks {
             let payload = commands::chunk_to_payload(chunk);
-            let shape = random_shape();
+            let shape = pick_shape(rng);
             pieces.push(Piece::with_payload(shape, payload));
         }
         (self.cycle, pieces)
@@ -50,52 +95,198 @@ impl CommandRun {
 }
 
 pub struct Game {
+    pub config: Config,
     pub board: Board,
-    pub current: Piece,
+    pub current: Option<Piece>,
     pub game_over: bool,
+    // Over, but the queued pieces are still playing out a cinematic ending.
+    pub ending: bool,
+    pub time_up: bool,
+    pub finish_time: Option<Duration>,
+    pub elapsed: Duration,
     pub score: u64,
+    // What the UI shows, counting up to `score` over a few frames after it changes.
+    pub shown_score: u64,
+    score_count_left: u8,
     pub lines_cleared: u64,
+    pub commands_run: u64,
+    last_start: Duration,
     pub pending_clear: Vec<usize>,
     pub clear_flash_frames: u8,
     pub lock_flash_cells: Vec<(usize, usize)>,
     pub lock_flash_frames: u8,
+    pub fail_flash_frames: u8,
+    pub garbage_rise_rows: usize,
+    pub garbage_rise_frames: u8,
+    pub entry_delay: u8,
+    pub(crate) buffered_actions: Vec<Action>,
+    pub spawn_grace: u8,
+    first_spawn: bool,
+    pub lock_delay: Option<u8>,
+    lock_resets: u32,
     piece_queue: VecDeque<QueuedPiece>,
-    pub active_piece: bool,
     active_run: Option<u64>,
-    active_runs: HashMap<u64, CommandRun>,
+    // Ordered by run id so refills interleave runs in arrival order.
+    active_runs: BTreeMap<u64, CommandRun>,
+    pub priority_run: Option<u64>,
+    ignored_runs: HashSet<u64>,
+    merged_runs: HashMap<u64, u64>,
+    // Run of the most recent counted START, the only one a START can merge into.
+    last_run: Option<u64>,
     pub bombs: i32,
+    pub freeze_left: Duration,
+    pub(crate) reward_turn: usize,
+    // Shuffles paid out since the current piece spawned.
+    pub(crate) shuffles: u32,
     pub current_is_bomb: bool,
+    // Queue entry the current piece spawned from, restored if an inject displaces it.
+    current_run_id: u64,
+    current_cycle: u64,
     pub variety_meter: i32,
     pub last_cmd_identity: Option<String>,
     pub variety_streak: i32,
+    pub fail_streak: u32,
+    pub recent_inputs: VecDeque<(Duration, Input)>,
+    failures: HashMap<String, u32>,
+    // First letter of each run's identity, kept after the run ends so its
+    // locked cells can still be labelled.
+    origin_labels: HashMap<u64, char>,
+    pace: PaceTracker,
+    success: SuccessRate,
+    pub(super) blitz: BlitzTracker,
+    pub falling_behind: bool,
+    pub(crate) last_garbage_hole: Option<usize>,
+    pub column_locks: Vec<u32>,
+    // Seed for every random choice (shapes, garbage holes, infection), so a
+    // recorded session replays identically.
+    pub seed: u64,
+    pub(crate) rng: StdRng,
+    // Shapes draw from their own stream so garbage and infection rolls never
+    // change which pieces are coming, and the preview stays accurate.
+    shape_rng: StdRng,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopOut {
+    BlockOut,
+    LockOut,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreviewPiece {
+    pub shape: Shape,
+    pub run_id: Option<u64>,
+    pub is_bomb: bool,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        Self::seeded(config, seed)
+    }
+
+    pub fn seeded(config: Config, seed: u64) -> Self {
         let board = Board::new(BOARD_W, BOARD_H);
-        let game = Self {
+        let snapshot = config.snapshot.clone();
+        let mut game = Self {
+            config,
             board,
-            current: Piece::with_payload(Shape::I, vec!['░'; CHUNK_SIZE]),
+            current: None,
             game_over: false,
+            ending: false,
+            time_up: false,
+            finish_time: None,
+            elapsed: Duration::ZERO,
             score: 0,
+            shown_score: 0,
+            score_count_left: 0,
             lines_cleared: 0,
+            commands_run: 0,
+            last_start: Duration::ZERO,
             pending_clear: Vec::new(),
             clear_flash_frames: 0,
             lock_flash_cells: Vec::new(),
             lock_flash_frames: 0,
+            fail_flash_frames: 0,
+            garbage_rise_rows: 0,
+            garbage_rise_frames: 0,
+            entry_delay: 0,
+            buffered_actions: Vec::new(),
+            spawn_grace: 0,
+            first_spawn: true,
+            lock_delay: None,
+            lock_resets: 0,
             piece_queue: VecDeque::new(),
-            active_piece: false,
             active_run: None,
-            active_runs: HashMap::new(),
+            active_runs: BTreeMap::new(),
+            priority_run: None,
+            ignored_runs: HashSet::new(),
+            merged_runs: HashMap::new(),
+            last_run: None,
             bombs: 0,
+            freeze_left: Duration::ZERO,
+            reward_turn: 0,
+            shuffles: 0,
             current_is_bomb: false,
+            current_run_id: 0,
+            current_cycle: 1,
             variety_meter: 0,
             last_cmd_identity: None,
             variety_streak: 0,
+            fail_streak: 0,
+            recent_inputs: VecDeque::new(),
+            failures: HashMap::new(),
+            origin_labels: HashMap::new(),
+            pace: PaceTracker::default(),
+            success: SuccessRate::default(),
+            blitz: BlitzTracker::default(),
+            falling_behind: false,
+            last_garbage_hole: None,
+            column_locks: vec![0; BOARD_W],
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            shape_rng: StdRng::seed_from_u64(seed ^ SHAPE_STREAM),
         };
+        if let Some(snap) = snapshot {
+            game.load_snapshot(&snap);
+        }
+        if let Some(scenario) = game.config.scenario.clone() {
+            game.start_scenario(&scenario);
+        }
         game
     }
 
+    fn start_scenario(&mut self, scenario: &Scenario) {
+        debug!("practice `{}`: {} pieces", scenario.name, scenario.pieces.len());
+        self.board = scenario.board.clone();
+        for &shape in &scenario.pieces {
+            self.piece_queue.push_back(QueuedPiece {
+                run_id: 0,
+                cycle: 1,
+                piece: Piece::with_payload(shape, vec![shape.letter(); CHUNK_SIZE]),
+                is_bomb: false,
+            });
+        }
+        self.spawn_next();
+    }
+
+    // Start a fresh session with the same config. The new seed is drawn from
+    // the old session's RNG so restarts replay deterministically too.
+    pub fn reset(&mut self) {
+        let seed = self.rng.r#gen();
+        *self = Self::seeded(self.config.clone(), seed);
+    }
+
     pub fn can_place(&self, piece: &Piece) -> bool {
         for (x, y, _) in piece.cells() {
             if x < 0 || y < 0 {
@@ -105,7 +296,7 @@ impl Game {
             if xu >= self.board.width || yu >= self.board.height {
                 return false;
             }
-            if let Cell::Filled(_, _) = self.board.get(xu, yu) {
+            if self.board.get(xu, yu).is_filled() {
                 return false;
             }
         }
@@ -113,52 +304,74 @@ impl Game {
     }
 
     pub fn lock_piece(&mut self) {
+        let Some(piece) = self.current.take() else {
+            return;
+        };
+        self.lock_delay = None;
+        self.lock_resets = 0;
         self.lock_flash_cells.clear();
-        for (x, y, (left, right)) in self.current.cells_with_pairs() {
+        let origin = self.active_run;
+        for (x, y, (left, right)) in piece.cells_with_pairs() {
             if x >= 0 && y >= 0 {
                 let (xu, yu) = (x as usize, y as usize);
                 if xu < self.board.width && yu < self.board.height {
-                    self.board.set(xu, yu, Cell::Filled(left, right));
+                    self.board.set_with_origin(xu, yu, Cell::Filled(left, right), origin);
                     self.lock_flash_cells.push((xu, yu));
                 }
             }
         }
+        let mut columns: Vec<usize> = self.lock_flash_cells.iter().map(|&(x, _)| x).collect();
+        columns.sort_unstable();
+        columns.dedup();
+        for x in columns {
+            self.column_locks[x] += 1;
+        }
         self.lock_flash_frames = 1;
         self.active_run = None;
-        self.active_piece = false;
+        self.pace.record_lock(self.elapsed);
         let full_rows: Vec<usize> = (0..self.board.height)
-            .filter(|y| (0..self.board.width).all(|x| matches!(self.board.get(x, *y), Cell::Filled(_, _))))
+            .filter(|y| (0..self.board.width).all(|x| self.board.get(x, *y).is_filled()))
             .collect();
+        trace!("locked {:?} at {:?}, full rows {:?}", piece.shape, self.lock_flash_cells, full_rows);
         if !full_rows.is_empty() {
             self.pending_clear = full_rows;
             self.clear_flash_frames = 2;
         }
 
         if self.current_is_bomb {
-            self.apply_bomb_clear();
+            self.apply_bomb_clear(&piece);
         }
     }
 
     pub fn move_current(&mut self, dx: i32, dy: i32) -> bool {
-        if self.game_over {
+        if self.frozen() {
             return false;
         }
-        let next = self.current.shifted(dx, dy);
+        let Some(next) = self.current.as_ref().map(|p| p.shifted(dx, dy)) else {
+            return false;
+        };
         if self.can_place(&next) {
-            self.current = next;
+            self.current = Some(next);
             true
         } else {
             false
         }
     }
 
+    // True when the piece turned. An O never does: it is left exactly where it
+    // is, without placement checks.
     pub fn rotate_current(&mut self) -> bool {
         if self.game_over {
             return false;
         }
-        let next = self.current.rotated();
+        if self.current.as_ref().is_some_and(|p| p.shape == Shape::O) {
+            return false;
+        }
+        let Some(next) = self.current.as_ref().map(Piece::rotated) else {
+            return false;
+        };
         if self.can_place(&next) {
-            self.current = next;
+            self.current = Some(next);
             true
         } else {
             false
@@ -166,146 +379,572 @@ impl Game {
     }
 
     pub fn tick_gravity(&mut self) {
-        if self.game_over {
+        if self.frozen() || self.current.is_none() || self.spawn_grace > 0 {
             return;
         }
-        if !self.active_piece {
+        // The clock stops at game over, so nothing timed may hold up an ending.
+        if !self.ending && (!self.freeze_left.is_zero() || self.idle_paused()) {
             return;
         }
-        if !self.move_current(0, 1) {
+        if self.move_current(0, self.config.gravity.dy()) {
+            self.lock_delay = None;
+        } else if self.config.lock_delay_frames == 0 || self.ending {
             self.lock_piece();
-            self.spawn_next();
+            self.spawn_after_lock();
+        } else if self.lock_delay.is_none() {
+            self.lock_delay = Some(self.config.lock_delay_frames);
+        }
+    }
+
+    // A move or rotation landed while the piece is grounded: restart the lock
+    // delay, unless the piece has used up its resets. Then the delay keeps
+    // running, so spinning in place can't stall it forever.
+    pub(super) fn reset_lock_delay(&mut self) {
+        if self.lock_delay.is_none() || self.lock_resets >= self.config.lock_reset_cap {
+            return;
+        }
+        self.lock_resets += 1;
+        self.lock_delay = Some(self.config.lock_delay_frames);
+    }
+
+    // One step with gravity. It never locks: a piece held against the stack
+    // still waits for the gravity tick, so holding soft drop keeps lock timing.
+    pub fn soft_drop(&mut self) -> bool {
+        let moved = self.move_current(0, self.config.gravity.dy());
+        if moved {
+            self.lock_delay = None;
         }
+        moved
     }
 
     pub fn hard_drop(&mut self) {
         if self.game_over {
             return;
         }
-        if !self.active_piece {
+        if self.current.is_none() {
             return;
         }
-        while self.move_current(0, 1) {}
+        while self.move_current(0, self.config.gravity.dy()) {}
         self.lock_piece();
-        self.spawn_next();
+        self.spawn_after_lock();
+    }
+
+    fn spawn_after_lock(&mut self) {
+        if self.config.are_frames == 0 {
+            self.spawn_next();
+        } else {
+            self.entry_delay = self.config.are_frames;
+        }
+    }
+
+    pub fn advance_clock(&mut self, dt: Duration) {
+        if self.game_over {
+            return;
+        }
+        self.elapsed += dt;
+        self.freeze_left = self.freeze_left.saturating_sub(dt);
+        if let Some(limit) = self.config.time_limit
+            && self.elapsed >= limit
+        {
+            self.elapsed = limit;
+            self.time_up = true;
+            self.end_game();
+        }
+    }
+
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.config
+            .time_limit
+            .map(|limit| limit.saturating_sub(self.elapsed))
     }
 
     pub fn process_effects(&mut self) {
         if self.lock_flash_frames > 0 {
             self.lock_flash_frames -= 1;
         }
+        if self.fail_flash_frames > 0 {
+            self.fail_flash_frames -= 1;
+        }
+        if self.garbage_rise_frames > 0 {
+            self.garbage_rise_frames -= 1;
+            if self.garbage_rise_frames == 0 {
+                self.garbage_rise_rows = 0;
+            }
+        }
+        self.count_up_score();
         if self.clear_flash_frames > 0 {
             self.clear_flash_frames -= 1;
             if self.clear_flash_frames == 0 && !self.pending_clear.is_empty() {
                 self.perform_pending_clear();
             }
         }
+        if self.spawn_grace > 0 {
+            self.spawn_grace -= 1;
+        }
+        if self.config.behind_warning {
+            self.falling_behind = !self.game_over && self.pace.falling_behind(self.elapsed);
+        }
+        if self.entry_delay > 0 && self.pending_clear.is_empty() && !self.frozen() {
+            self.entry_delay -= 1;
+            if self.entry_delay == 0 {
+                self.spawn_next();
+                for action in std::mem::take(&mut self.buffered_actions) {
+                    self.apply_action(action);
+                }
+            }
+        }
+        if let Some(left) = self.lock_delay
+            && self.freeze_left.is_zero()
+            && !self.idle_paused()
+            && !self.game_over
+        {
+            self.lock_delay = left.checked_sub(1).filter(|&n| n > 0);
+            if self.lock_delay.is_none() {
+                // Still resting on something: lock. Slid off a ledge: let gravity take it.
+                let below = self.current.as_ref().map(|p| p.shifted(0, self.config.gravity.dy()));
+                if below.is_some_and(|p| !self.can_place(&p)) {
+                    self.lock_piece();
+                    self.spawn_after_lock();
+                }
+            }
+        }
+        // The ending is over once the last queued piece has locked and cleared.
+        if self.ending && self.current.is_none() && self.entry_delay == 0 && self.pending_clear.is_empty() {
+            self.ending = false;
+        }
+    }
+
+    // Close the gap between `shown_score` and `score` evenly over the
+    // configured frames, restarting the count if the score moves again.
+    fn count_up_score(&mut self) {
+        if self.shown_score >= self.score || self.config.score_count_frames == 0 {
+            self.shown_score = self.score;
+            self.score_count_left = 0;
+            return;
+        }
+        if self.score_count_left == 0 {
+            self.score_count_left = self.config.score_count_frames;
+        }
+        let gap = self.score - self.shown_score;
+        self.shown_score += gap.div_ceil(u64::from(self.score_count_left));
+        self.score_count_left -= 1;
     }
 
     pub fn spawn_next(&mut self) {
-        self.ensure_queue();
-        if let Some(qp) = self.piece_queue.pop_front() {
-            self.active_piece = true;
+        // An ending plays out what was queued; repeat cycles don't refill it.
+        if !self.ending {
+            self.ensure_queue();
+        }
+        self.shuffles = 0;
+        if let Some(mut qp) = self.piece_queue.pop_front() {
+            self.orient_for_spawn(&mut qp.piece);
             self.active_run = if qp.is_bomb { None } else { Some(qp.run_id) };
             self.current_is_bomb = qp.is_bomb;
-            if self.can_place(&qp.piece) {
-                self.current = qp.piece;
-            } else {
-                self.game_over = true;
+            (self.current_run_id, self.current_cycle) = (qp.run_id, qp.cycle);
+            if !self.can_place(&qp.piece) && self.config.spawn_collision == SpawnCollision::Crush {
+                self.crush_spawn_rows(&qp.piece);
+            }
+            if !self.can_place(&qp.piece) {
+                debug!("spawn {:?} for run {} collides", qp.piece.shape, qp.run_id);
+                if self.check_top_out(TopOut::BlockOut) {
+                    self.current = None;
+                    self.ending = false;
+                    return;
+                }
+                self.crush_spawn_rows(&qp.piece);
+            }
+            debug!("spawn {:?} for run {} (bomb: {})", qp.piece.shape, qp.run_id, qp.is_bomb);
+            self.current = Some(qp.piece);
+            self.spawn_grace = self.config.spawn_grace_frames;
+            if std::mem::take(&mut self.first_spawn) {
+                self.spawn_grace = self.spawn_grace.max(self.config.first_spawn_grace_frames);
             }
         } else {
-            self.active_piece = false;
+            self.current = None;
             self.active_run = None;
             self.current_is_bomb = false;
         }
     }
 
-    pub(crate) fn ghost_piece(&self) -> Piece {
-        let mut ghost = self.current.clone();
+    fn orient_for_spawn(&self, piece: &mut Piece) {
+        piece.rotation = self.config.spawn_rotation[piece.shape.index()];
+        if self.config.gravity == GravityDir::Up {
+            // Rise from the floor: sit the piece's lowest cell on the bottom row.
+            let lowest = piece.cells().iter().map(|&(_, y, _)| y).max().unwrap_or(0);
+            piece.y += self.board.height as i32 - 1 - lowest;
+        }
+    }
+
+    // Debug entry point: swap in a piece of `shape` at the spawn edge, its
+    // leftmost cell in column `col`, bypassing the command source. The piece
+    // it replaces goes back to the front of the queue. A column it can't fit
+    // in is refused; one blocked by the stack is a block-out like any spawn.
+    pub fn inject_piece(&mut self, shape: Shape, col: i32, payload: Vec<char>) -> bool {
+        if self.game_over {
+            return false;
+        }
+        let mut piece = Piece::with_payload(shape, payload);
+        self.orient_for_spawn(&mut piece);
+        let leftmost = piece.cells().iter().map(|&(x, _, _)| x).min().unwrap_or(0);
+        piece.x += col - leftmost;
+        let inside = piece
+            .cells()
+            .iter()
+            .all(|&(x, y, _)| x >= 0 && (x as usize) < self.board.width && y >= 0 && (y as usize) < self.board.height);
+        if !inside {
+            debug!("inject {shape:?} at column {col}: outside the well");
+            return false;
+        }
+        if !self.can_place(&piece) {
+            debug!("inject {shape:?} at column {col}: blocked");
+            if self.check_top_out(TopOut::BlockOut) {
+                self.current = None;
+            }
+            return false;
+        }
+        if let Some(old) = self.current.take() {
+            self.piece_queue.push_front(QueuedPiece {
+                run_id: self.current_run_id,
+                cycle: self.current_cycle,
+                piece: old,
+                is_bomb: self.current_is_bomb,
+            });
+        }
+        debug!("inject {shape:?} at column {col}");
+        self.current = Some(piece);
+        self.active_run = None;
+        self.current_is_bomb = false;
+        (self.current_run_id, self.current_cycle) = (0, 1);
+        self.lock_delay = None;
+        self.lock_resets = 0;
+        self.spawn_grace = self.config.spawn_grace_frames;
+        true
+    }
+
+    pub(super) fn reshuffle_queue(&mut self) {
+        for qp in self.piece_queue.iter_mut().filter(|qp| !qp.is_bomb) {
+            qp.piece.shape = pick_shape(&mut self.rng);
+        }
+    }
+
+    // The run a START of `identity` folds into: the one the previous START
+    // went to, if it ran the same command within the merge window.
+    fn merge_target(&self, identity: &str) -> Option<u64> {
+        let window = self.config.merge_window?;
+        let run = self.active_runs.get(&self.last_run?)?;
+        (run.identity == identity && self.elapsed.saturating_sub(run.started_at) <= window).then_some(run.id)
+    }
+
+    // Every top-out goes through here: the game ends if the configured rule
+    // counts this kind, and the caller recovers otherwise. A crushed spawn
+    // always fits, so the only other way out is garbage overflow.
+    pub(super) fn check_top_out(&mut self, top_out: TopOut) -> bool {
+        let ends = self.config.top_out.ends_game(top_out);
+        debug!("{top_out:?} (game over: {ends})");
+        if ends {
+            self.end_game();
+        }
+        ends
+    }
+
+    // Every way the game ends comes through here. A cinematic ending only
+    // starts if there is a piece falling or about to spawn.
+    pub(super) fn end_game(&mut self) {
+        if !self.game_over && self.config.ending == Ending::Cinematic {
+            self.ending = self.current.is_some() || self.entry_delay > 0;
+            debug!("game over: cinematic ending (pieces left: {})", self.piece_queue.len());
+        }
+        self.game_over = true;
+    }
+
+    pub fn frozen(&self) -> bool {
+        self.game_over && !self.ending
+    }
+
+    pub(crate) fn ghost_piece(&self) -> Option<Piece> {
+        let mut ghost = self.current.clone()?;
         while {
-            let next = ghost.shifted(0, 1);
+            let next = ghost.shifted(0, self.config.gravity.dy());
             self.can_place(&next)
         } {
-            ghost.y += 1;
+            ghost.y += self.config.gravity.dy();
         }
-        ghost
+        Some(ghost)
     }
 
     pub fn handle_command_event(&mut self, ev: CommandEvent) {
+        if self.config.scenario.is_some() {
+            debug!("practice: ignoring {ev:?}");
+            return;
+        }
         match ev {
             CommandEvent::Start { id, command } => {
-                let chunks = commands::command_to_chunks(&command);
-                let identity = command_identity(&command);
-                let mut run = CommandRun::new(id, chunks, identity.clone());
-                let (cycle, pieces) = run.next_cycle_pieces();
+                let identity = command_identity(&command, &self.config.aliases);
+                if let Some(&action) = self.config.command_actions.get(&identity) {
+                    // A move, not a run: its END is dropped like a filtered one's.
+                    debug!("start {id} `{command}` plays {}", action.name());
+                    self.ignored_runs.insert(id);
+                    self.apply_action(action);
+                    return;
+                }
+                if !self.config.counts_command(&command) {
+                    debug!("start {id} `{command}` filtered out");
+                    self.ignored_runs.insert(id);
+                    return;
+                }
+                let mut chunks = commands::command_to_chunks(&command);
+                if let Some(cap) = self.config.max_pieces_per_cycle {
+                    chunks.truncate(cap);
+                }
+                self.commands_run += 1;
+                self.last_start = self.elapsed;
+                let run_id = match self.merge_target(&identity) {
+                    Some(run_id) => {
+                        debug!("start {id} `{command}`: merged into run {run_id}");
+                        self.merged_runs.insert(id, run_id);
+                        if let Some(run) = self.active_runs.get_mut(&run_id) {
+                            run.rejoin(chunks, self.elapsed);
+                        }
+                        run_id
+                    }
+                    None => {
+                        debug!("start {id} `{command}`: {} chunks, identity `{identity}`", chunks.len());
+                        self.active_runs.insert(id, CommandRun::new(id, chunks, identity.clone(), self.elapsed));
+                        if let Some(first) = identity.chars().next() {
+                            self.origin_labels.insert(id, first.to_ascii_lowercase());
+                        }
+                        id
+                    }
+                };
+                let Some(run) = self.active_runs.get_mut(&run_id) else {
+                    return;
+                };
+                let (cycle, pieces) = run.next_cycle_pieces(&mut self.shape_rng);
+                self.pace.record_queued(self.elapsed, pieces.len() as u32);
                 for p in pieces {
                     self.piece_queue.push_back(QueuedPiece {
-                        run_id: id,
+                        run_id,
                         cycle,
                         piece: p,
                         is_bomb: false,
                     });
                 }
-                self.active_runs.insert(id, run);
+                self.last_run = Some(run_id);
                 self.last_cmd_identity.get_or_insert(identity);
-                if !self.active_piece {
+                if self.current.is_none() && self.entry_delay == 0 {
                     self.spawn_next();
                 }
             }
-            CommandEvent::End { id, _exit_code } => {
+            CommandEvent::End {
+                id,
+                exit_code,
+                stderr_lines,
+                duration_ms,
+            } => {
+                if self.ignored_runs.remove(&id) {
+                    return;
+                }
+                let id = match self.merged_runs.remove(&id) {
+                    Some(run_id) => {
+                        debug!("end {id} belongs to merged run {run_id}");
+                        run_id
+                    }
+                    None => id,
+                };
                 let identity = self.active_runs.get(&id).map(|r| r.identity.clone());
-                if let Some(run) = self.active_runs.get_mut(&id) {
-                    run.active = false;
+                let took = duration_ms
+                    .map(Duration::from_millis)
+                    .or_else(|| self.active_runs.get(&id).map(|r| self.elapsed.saturating_sub(r.started_at)));
+                debug!("end {id} exit {exit_code} (known run: {})", identity.is_some());
+                let still_open = match self.active_runs.get_mut(&id) {
+                    Some(run) => {
+                        run.open = run.open.saturating_sub(1);
+                        run.active = run.open > 0;
+                        run.active
+                    }
+                    None => false,
+                };
+                if !still_open {
+                    if self.priority_run == Some(id) {
+                        self.priority_run = None;
+                    }
+                    // Drop queued pieces from repeat cycles for this run.
+                    self.piece_queue
+                        .retain(|qp| qp.run_id != id || qp.cycle <= 1);
                 }
-                // Drop queued pieces from repeat cycles for this run.
-                self.piece_queue
-                    .retain(|qp| qp.run_id != id || qp.cycle <= 1);
 
-                if _exit_code != 0 {
-                    self.apply_garbage_row();
-                    self.apply_infection();
+                let failed = self.config.is_failure(exit_code);
+                if failed {
+                    let effect = self.config.exit_effect(exit_code);
+                    debug!("run {id} exit {exit_code}: {effect:?}");
+                    self.fail_streak += 1;
+                    self.fail_flash_frames = self.config.fail_flash_frames;
+                    for _ in 0..effect.garbage_rows {
+                        self.apply_garbage_row();
+                    }
+                    if effect.infect {
+                        self.apply_infection();
+                    }
+                } else if exit_code != 0 {
+                    debug!("run {id} exit {exit_code}: cancelled, no penalty");
+                } else {
+                    self.fail_streak = 0;
+                    if let (Some(took), Some(limit)) = (took, self.config.slow_command)
+                        && took > limit
+                    {
+                        debug!("run {id} succeeded but took {took:?}: garbage");
+                        self.apply_garbage_row();
+                    }
+                }
+                if let (Some(lines), Some(per_row)) = (stderr_lines, self.config.stderr_lines_per_row) {
+                    self.apply_garbage_rows((lines / per_row).min(STDERR_ROW_CAP));
                 }
                 if let Some(id_str) = identity {
-                    self.apply_variety(&id_str, _exit_code);
+                    // A cancelled run neither passed nor failed.
+                    if failed || exit_code == 0 {
+                        self.success.record(!failed);
+                    }
+                    if failed {
+                        *self.failures.entry(id_str.clone()).or_default() += 1;
+                    }
+                    self.apply_variety(&id_str, exit_code);
                     self.last_cmd_identity = Some(id_str);
                 }
             }
         }
     }
 
-    pub fn is_running(&self) -> bool {
-        self.active_piece
-            || !self.piece_queue.is_empty()
-            || self.active_runs.values().any(|r| r.active)
+    // The `limit` commands that failed most often, worst first; ties go
+    // alphabetically so the list is stable.
+    pub fn failure_digest(&self, limit: usize) -> Vec<(&str, u32)> {
+        let mut ranked: Vec<(&str, u32)> = self.failures.iter().map(|(name, &n)| (name.as_str(), n)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked.truncate(limit);
+        ranked
     }
 
-    fn ensure_queue(&mut self) {
-        if !self.piece_queue.is_empty() {
-            return;
+    // Rows the stack is drawn short of where it is while new garbage rises,
+    // shrinking evenly to zero over the rise.
+    pub fn garbage_rise_offset(&self) -> usize {
+        let total = usize::from(self.config.garbage_rise_frames);
+        if total == 0 {
+            return 0;
         }
-        for run in self.active_runs.values_mut() {
-            if run.active {
-                let (cycle, pieces) = run.next_cycle_pieces();
-                for p in pieces {
-                    self.piece_queue.push_back(QueuedPiece {
-                        run_id: run.id,
-                        cycle,
-                        piece: p,
-                        is_bomb: false,
-                    });
-                }
-            }
+        (self.garbage_rise_rows * usize::from(self.garbage_rise_frames)).div_ceil(total)
+    }
+
+    // No command has started within the idle window: gravity and the lock
+    // delay hold until the next START, though the piece can still be moved.
+    pub fn idle_paused(&self) -> bool {
+        self.config
+            .idle_pause
+            .is_some_and(|window| self.elapsed.saturating_sub(self.last_start) >= window)
+    }
+
+    pub fn commands_per_line(&self) -> Option<f64> {
+        (self.lines_cleared > 0).then(|| self.commands_run as f64 / self.lines_cleared as f64)
+    }
+
+    pub fn stack_height(&self) -> usize {
+        self.board.stack_height(self.config.gravity == GravityDir::Up)
+    }
+
+    pub fn success_rate(&self) -> Option<u32> {
+        self.success.percent()
+    }
+
+    pub fn active_run(&self) -> Option<u64> {
+        self.active_run
+    }
+
+    pub fn origin_label(&self, run: u64) -> Option<char> {
+        self.origin_labels.get(&run).copied()
+    }
+
+    pub fn level(&self) -> u64 {
+        self.lines_cleared / LINES_PER_LEVEL + 1
+    }
+
+    pub fn score_snapshot(&self) -> ScoreSnapshot {
+        ScoreSnapshot {
+            score: self.score,
+            lines: self.lines_cleared,
+            level: self.level(),
         }
-        if self.piece_queue.is_empty() && self.bombs > 0 {
-            let bomb = Self::make_bomb_piece();
-            self.piece_queue.push_back(QueuedPiece {
-                run_id: 0,
-                cycle: 0,
-                piece: bomb,
-                is_bomb: true,
+    }
+
+    pub fn pending_pieces(&self) -> usize {
+        self.piece_queue.len() + self.active_runs.values().filter(|r| r.active).count()
+    }
+
+    // Step the priority to the next active run in arrival order, wrapping back
+    // to no priority after the last one.
+    pub fn cycle_priority(&mut self) {
+        let current = self.priority_run;
+        self.priority_run = self
+            .active_runs
+            .values()
+            .filter(|r| r.active)
+            .map(|r| r.id)
+            .find(|&id| current.is_none_or(|p| id > p));
+        debug!("priority run: {:?}", self.priority_run);
+        self.promote_priority();
+    }
+
+    pub fn priority_identity(&self) -> Option<&str> {
+        let id = self.priority_run?;
+        self.active_runs.get(&id).map(|r| r.identity.as_str())
+    }
+
+    fn promote_priority(&mut self) {
+        if let Some(id) = self.priority_run {
+            promote_run(&mut self.piece_queue, id);
+        }
+    }
+
+    // The next `n` pieces to spawn, including the refills `ensure_queue` will
+    // make from still-running commands, computed on copies of the queue state.
+    // New commands or restarts can still change what comes after the queue.
+    pub fn peek_next(&self, n: usize) -> Vec<PreviewPiece> {
+        let mut queue = self.piece_queue.clone();
+        let mut runs = self.active_runs.clone();
+        let mut rng = self.shape_rng.clone();
+        let mut bombs = self.bombs;
+        let mut preview = Vec::with_capacity(n);
+        while preview.len() < n {
+            let (shape, depth) = (self.config.bomb_shape, self.config.preview_lead);
+            fill_queue(&mut queue, &mut runs, &mut rng, self.priority_run, &mut bombs, shape, depth);
+            let Some(qp) = queue.pop_front() else {
+                break;
+            };
+            preview.push(PreviewPiece {
+                shape: qp.piece.shape,
+                run_id: (!qp.is_bomb).then_some(qp.run_id),
+                is_bomb: qp.is_bomb,
             });
-            self.bombs -= 1;
         }
+        preview
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.current.is_some()
+            || !self.piece_queue.is_empty()
+            || self.active_runs.values().any(|r| r.active)
+    }
+
+    // Top the queue up so `preview_lead` pieces wait behind the next spawn while
+    // commands are running; with the default lead of 0 it only refills once dry.
+    fn ensure_queue(&mut self) {
+        let queued = fill_queue(
+            &mut self.piece_queue,
+            &mut self.active_runs,
+            &mut self.shape_rng,
+            self.priority_run,
+            &mut self.bombs,
+            self.config.bomb_shape,
+            self.config.preview_lead,
+        );
+        self.pace.record_queued(self.elapsed, queued);
     }
 
     fn add_score(&mut self, cleared: u64) {
@@ -319,40 +958,683 @@ impl Game {
         self.score += add;
     }
 
-    fn make_bomb_piece() -> Piece {
-        // Use O piece for compact 2x2 bomb footprint with solid payload.
-        Piece::with_payload(Shape::O, vec!['▓'; CHUNK_SIZE])
+    // Solid payload in the configured footprint; every cell it covers is a
+    // blast center when it lands.
+    fn make_bomb_piece(shape: Shape) -> Piece {
+        Piece::with_payload(shape, vec!['▓'; CHUNK_SIZE])
     }
 
-    fn perform_pending_clear(&mut self) {
+    pub(super) fn perform_pending_clear(&mut self) {
         let cleared = self.pending_clear.len() as u64;
         if cleared == 0 {
             return;
         }
-        let mut new_cells = Vec::with_capacity(self.board.cells.len());
-        for y in 0..self.board.height {
-            if self.pending_clear.contains(&y) {
-                continue;
+        let rows = std::mem::take(&mut self.pending_clear);
+        self.clear_rows(&rows);
+        // The final score was settled when the game ended.
+        if self.ending {
+            return;
+        }
+        self.lines_cleared += cleared;
+        self.add_score(cleared);
+        if self.config.clear_variety > 0 {
+            self.add_variety(self.config.clear_variety * cleared as i32);
+            debug!("cleared {cleared}: variety meter {}, bombs {}", self.variety_meter, self.bombs);
+        }
+        if let Some(goal) = self.config.line_goal
+            && self.lines_cleared >= goal
+            && !self.game_over
+        {
+            debug!("line goal {goal} reached at {:?}", self.elapsed);
+            self.finish_time = Some(self.elapsed);
+            self.end_game();
+        }
+    }
+
+    pub(super) fn clear_rows(&mut self, rows: &[usize]) {
+        self.board.remove_rows(rows, self.config.gravity == GravityDir::Up);
+    }
+
+    // Last-chance top-out: wipe every row the spawning piece covers. Unscored.
+    fn crush_spawn_rows(&mut self, piece: &Piece) {
+        // Everything between the spawn edge and the piece's far side.
+        let ys = piece.cells().iter().map(|&(_, y, _)| y).collect::<Vec<_>>();
+        let rows: Vec<usize> = match self.config.gravity {
+            GravityDir::Down => {
+                let bottom = ys.iter().copied().max().unwrap_or(0);
+                (0..=bottom.max(0) as usize).filter(|&y| y < self.board.height).collect()
             }
-            for x in 0..self.board.width {
-                new_cells.push(self.board.get(x, y));
+            GravityDir::Up => {
+                let top = ys.iter().copied().min().unwrap_or(0).max(0) as usize;
+                (top..self.board.height).collect()
             }
+        };
+        self.clear_rows(&rows);
+        self.pending_clear.retain(|y| !rows.contains(y));
+    }
+}
+
+// Refill an empty queue: one cycle from every running command, the
+// prioritized run first, or a bomb when nothing is running. Returns how many
+// command pieces were added.
+fn refill_queue(
+    queue: &mut VecDeque<QueuedPiece>,
+    runs: &mut BTreeMap<u64, CommandRun>,
+    rng: &mut StdRng,
+    priority: Option<u64>,
+    bombs: &mut i32,
+    bomb_shape: Shape,
+) -> u32 {
+    let before = queue.len();
+    for run in runs.values_mut().filter(|r| r.active) {
+        let (cycle, pieces) = run.next_cycle_pieces(rng);
+        for p in pieces {
+            queue.push_back(QueuedPiece {
+                run_id: run.id,
+                cycle,
+                piece: p,
+                is_bomb: false,
+            });
         }
-        for _ in 0..cleared {
-            for _ in 0..self.board.width {
-                new_cells.insert(0, Cell::Empty);
-            }
+    }
+    let queued = (queue.len() - before) as u32;
+    if let Some(id) = priority {
+        promote_run(queue, id);
+    }
+    if queue.is_empty() && *bombs > 0 {
+        queue.push_back(QueuedPiece {
+            run_id: 0,
+            cycle: 0,
+            piece: Game::make_bomb_piece(bomb_shape),
+            is_bomb: true,
+        });
+        *bombs -= 1;
+    }
+    queued
+}
+
+// Refill until the queue holds more than `depth` pieces, so `depth` remain
+// behind the one about to spawn; stops early once the running commands add
+// nothing.
+fn fill_queue(
+    queue: &mut VecDeque<QueuedPiece>,
+    runs: &mut BTreeMap<u64, CommandRun>,
+    rng: &mut StdRng,
+    priority: Option<u64>,
+    bombs: &mut i32,
+    bomb_shape: Shape,
+    depth: usize,
+) -> u32 {
+    let mut queued = 0;
+    while queue.len() <= depth {
+        let before = queue.len();
+        queued += refill_queue(queue, runs, rng, priority, bombs, bomb_shape);
+        if queue.len() == before {
+            break;
         }
-        self.board.cells = new_cells;
-        self.lines_cleared += cleared;
-        self.add_score(cleared);
-        self.pending_clear.clear();
     }
+    queued
 }
 
-fn command_identity(cmd: &str) -> String {
-    commands::tokenize_command(cmd)
+fn promote_run(queue: &mut VecDeque<QueuedPiece>, id: u64) {
+    let (mut promoted, rest): (VecDeque<_>, VecDeque<_>) =
+        queue.drain(..).partition(|qp| !qp.is_bomb && qp.run_id == id);
+    promoted.extend(rest);
+    *queue = promoted;
+}
+
+// The program a command runs, without its directory and with aliases
+// resolved, so `/usr/bin/git` and `g` both count as `git`.
+fn command_identity(cmd: &str, aliases: &HashMap<String, String>) -> String {
+    let first = commands::tokenize_command(cmd)
         .into_iter()
         .next()
-        .unwrap_or_default()
+        .unwrap_or_default();
+    let name = first.rsplit('/').next().unwrap_or_default();
+    aliases.get(name).cloned().unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Pattern;
+    use crate::config::TopOutRule;
+    use crate::game::ROTATIONS;
+    use crate::testing::*;
+
+    fn start(game: &mut Game, id: u64, command: &str) {
+        game.handle_command_event(CommandEvent::Start {
+            id,
+            command: command.to_string(),
+        });
+    }
+
+    fn end(game: &mut Game, id: u64, code: i32) {
+        game.handle_command_event(CommandEvent::End {
+            id,
+            exit_code: code,
+            stderr_lines: None,
+            duration_ms: None,
+        });
+    }
+
+    #[test]
+    fn include_filter_miss_spawns_no_pieces() {
+        let mut game = game_with(|c| c.include = vec![Pattern::new("^cargo").unwrap()]);
+        start(&mut game, 1, "git status");
+        assert!(game.current.is_none());
+        assert!(game.piece_queue.is_empty());
+        start(&mut game, 2, "cargo build");
+        assert!(game.current.is_some());
+    }
+
+    #[test]
+    fn locking_in_one_column_tallies_that_column() {
+        let mut game = Game::new();
+        for _ in 0..3 {
+            assert!(game.inject_piece(Shape::O, 4, vec!['o'; 8]));
+            game.hard_drop();
+        }
+        assert_eq!(game.column_locks[4], 3);
+        assert_eq!(game.column_locks[5], 3);
+        assert_eq!(game.column_locks.iter().sum::<u32>(), 6);
+        game.game_over = true;
+        game.apply_action(Action::Restart);
+        assert!(game.column_locks.iter().all(|&n| n == 0));
+    }
+
+    fn full_board() -> Board {
+        Board::from_ascii(&"#########.\n".repeat(BOARD_H), BOARD_W, BOARD_H).unwrap()
+    }
+
+    #[test]
+    fn spawn_into_a_full_top_tops_out_by_default() {
+        let mut game = Game::new();
+        game.board = full_board();
+        start(&mut game, 1, "cargo build");
+        assert!(game.game_over);
+        assert!(game.current.is_none());
+    }
+
+    #[test]
+    fn spawn_into_a_full_top_crushes_with_crush_mode() {
+        let mut game = game_with(|c| c.spawn_collision = SpawnCollision::Crush);
+        game.board = full_board();
+        start(&mut game, 1, "cargo build");
+        assert!(!game.game_over);
+        let piece = game.current.clone().unwrap();
+        assert!(game.can_place(&piece));
+        assert!(game.board.cells.iter().any(|c| c.is_filled()));
+    }
+
+    #[test]
+    fn time_attack_ends_the_game_when_the_limit_elapses() {
+        let mut game = game_with(|c| c.time_limit = Some(Duration::from_secs(2)));
+        start(&mut game, 1, "cargo build");
+        for _ in 0..19 {
+            game.apply_input(Input::Frame(Duration::from_millis(100)));
+        }
+        assert!(!game.game_over);
+        game.apply_input(Input::Frame(Duration::from_millis(150)));
+        assert!(game.game_over && game.time_up);
+        assert_eq!(game.elapsed, Duration::from_secs(2));
+        assert_eq!(game.time_remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn configured_spawn_rotation_applies_to_spawned_pieces() {
+        let mut game = game_with(|c| c.spawn_rotation = [2; Shape::ALL.len()]);
+        for id in 1..=3 {
+            start(&mut game, id, "cargo build");
+            assert_eq!(game.current.as_ref().unwrap().rotation, 2);
+            game.hard_drop();
+        }
+    }
+
+    #[test]
+    fn promoting_a_run_moves_its_pieces_to_the_front() {
+        let mut game = game_with(|c| c.preview_lead = 6);
+        start(&mut game, 1, "cargo build --release --workspace");
+        start(&mut game, 2, "npm run test -- --watch --coverage");
+        let runs = |game: &Game| game.piece_queue.iter().map(|qp| qp.run_id).collect::<Vec<_>>();
+        let before = runs(&game);
+        assert!(before.contains(&2) && before.iter().position(|&id| id == 1) < before.iter().position(|&id| id == 2));
+        while game.priority_run != Some(2) {
+            game.cycle_priority();
+        }
+        let after = runs(&game);
+        let promoted = after.iter().take_while(|&&id| id == 2).count();
+        assert_eq!(promoted, before.iter().filter(|&&id| id == 2).count());
+        let rest: Vec<u64> = before.iter().copied().filter(|&id| id != 2).collect();
+        assert_eq!(after[promoted..], rest[..]);
+        assert_eq!(game.priority_identity(), Some("npm"));
+    }
+
+    #[test]
+    fn aliased_commands_share_an_identity_and_build_no_streak() {
+        let aliases = HashMap::from([("g".to_string(), "git".to_string())]);
+        for cmd in ["g status", "git push", "/usr/bin/git log"] {
+            assert_eq!(command_identity(cmd, &aliases), "git");
+        }
+        let mut game = game_with(|c| c.aliases = aliases);
+        for (id, cmd) in (1..).zip(["git status", "g push", "/usr/bin/git log", "g pull"]) {
+            start(&mut game, id, cmd);
+            end(&mut game, id, 0);
+        }
+        assert_eq!(game.variety_streak, 0);
+    }
+
+    #[test]
+    fn end_with_a_high_stderr_count_adds_garbage() {
+        let mut game = game_with(|c| c.stderr_lines_per_row = Some(10));
+        let garbage = |game: &Game| game.board.cells.iter().filter(|&&c| c == Cell::Garbage).count();
+        start(&mut game, 1, "cargo build");
+        end(&mut game, 1, 0);
+        assert_eq!(garbage(&game), 0);
+        start(&mut game, 2, "cargo clippy");
+        game.handle_command_event(CommandEvent::End {
+            id: 2,
+            exit_code: 0,
+            stderr_lines: Some(35),
+            duration_ms: None,
+        });
+        assert_eq!(garbage(&game), 3 * (BOARD_W - 1));
+    }
+
+    #[test]
+    fn entry_delay_holds_the_next_spawn_for_the_configured_frames() {
+        let mut game = game_with(|c| c.are_frames = 3);
+        start(&mut game, 1, "cargo build --release --workspace");
+        assert!(game.current.is_some());
+        game.hard_drop();
+        for _ in 0..2 {
+            assert!(game.current.is_none());
+            game.process_effects();
+        }
+        assert!(game.current.is_none());
+        game.process_effects();
+        assert!(game.current.is_some());
+    }
+
+    #[test]
+    fn up_gravity_piece_rises_and_locks_against_the_ceiling() {
+        let mut game = game_with(|c| c.gravity = GravityDir::Up);
+        start(&mut game, 1, "make");
+        let spawn_y = game.current.as_ref().unwrap().y;
+        game.tick_gravity();
+        assert_eq!(game.current.as_ref().unwrap().y, spawn_y - 1);
+        end(&mut game, 1, 0);
+        for _ in 0..BOARD_H {
+            game.tick_gravity();
+        }
+        assert!(game.current.is_none());
+        assert!((0..BOARD_W).any(|x| game.board.get(x, 0).is_filled()));
+        let lowest = (0..BOARD_H).filter(|&y| (0..BOARD_W).any(|x| game.board.get(x, y).is_filled())).max();
+        assert!(lowest < Some(4));
+    }
+
+    #[test]
+    fn failure_digest_ranks_identities_by_failures() {
+        let mut game = Game::new();
+        let runs = [("npm test", 1), ("cargo test", 101), ("git push", 1), ("cargo build", 101), ("git pull", 1), ("ls", 0), ("ls", 2), ("cargo fmt", 1)];
+        for (id, (cmd, code)) in (1..).zip(runs) {
+            start(&mut game, id, cmd);
+            end(&mut game, id, code);
+        }
+        assert_eq!(game.failure_digest(3), vec![("cargo", 3), ("git", 2), ("ls", 1)]);
+        assert_eq!(game.failure_digest(10).len(), 4);
+    }
+
+    #[test]
+    fn gravity_waits_out_the_spawn_grace() {
+        let mut game = game_with(|c| c.spawn_grace_frames = 2);
+        start(&mut game, 1, "cargo build");
+        let y = |game: &Game| game.current.as_ref().unwrap().y;
+        let spawn_y = y(&game);
+        for _ in 0..2 {
+            game.tick_gravity();
+            assert_eq!(y(&game), spawn_y);
+            game.process_effects();
+        }
+        game.tick_gravity();
+        assert_eq!(y(&game), spawn_y + 1);
+    }
+
+    #[test]
+    fn a_fifty_chunk_command_is_capped_per_cycle() {
+        let command = "x".repeat(50 * CHUNK_SIZE);
+        assert_eq!(commands::command_to_chunks(&command).len(), 50);
+        let mut game = game_with(|c| c.max_pieces_per_cycle = Some(6));
+        start(&mut game, 1, &command);
+        assert_eq!(game.piece_queue.len() + usize::from(game.current.is_some()), 6);
+    }
+
+    #[test]
+    fn a_slow_success_adds_garbage_and_a_fast_one_does_not() {
+        let mut game = game_with(|c| c.slow_command = Some(Duration::from_secs(2)));
+        let garbage = |game: &Game| game.board.cells.iter().filter(|&&c| c == Cell::Garbage).count();
+        for (id, ms, rows) in [(1, 100, 0), (2, 5_000, 1)] {
+            start(&mut game, id, "cargo test");
+            game.handle_command_event(CommandEvent::End {
+                id,
+                exit_code: 0,
+                stderr_lines: None,
+                duration_ms: Some(ms),
+            });
+            assert_eq!(garbage(&game), rows * (BOARD_W - 1));
+        }
+    }
+
+    #[test]
+    fn peek_next_matches_the_spawn_sequence() {
+        let mut game = Game::new();
+        game.bombs = 1;
+        let next = |game: &mut Game| {
+            game.hard_drop();
+            game.current.as_ref().map(|p| PreviewPiece {
+                shape: p.shape,
+                run_id: game.active_run(),
+                is_bomb: game.current_is_bomb,
+            })
+        };
+        // While runs are open, the preview includes the refills they will make...
+        start(&mut game, 1, "cargo build --release");
+        start(&mut game, 2, "git status --short");
+        let preview = game.peek_next(8);
+        let spawned: Vec<_> = (0..8).map_while(|_| next(&mut game)).collect();
+        assert_eq!(spawned, preview);
+        // ...and once they end, what is left plays out with the bomb last.
+        end(&mut game, 2, 0);
+        end(&mut game, 1, 0);
+        let preview = game.peek_next(20);
+        let spawned: Vec<_> = std::iter::from_fn(|| next(&mut game)).collect();
+        assert_eq!(spawned, preview);
+        assert!(spawned.last().unwrap().is_bomb);
+    }
+
+    #[test]
+    fn reaching_the_line_goal_ends_the_game_and_records_the_time() {
+        let mut game = game_with(|c| c.line_goal = Some(1));
+        game.board = Board::from_ascii("....######", BOARD_W, BOARD_H).unwrap();
+        game.advance_clock(Duration::from_secs(3));
+        assert!(game.inject_piece(Shape::I, 0, vec!['i'; 8]));
+        game.hard_drop();
+        assert!(!game.game_over);
+        while !game.pending_clear.is_empty() {
+            game.process_effects();
+        }
+        assert_eq!(game.lines_cleared, 1);
+        assert!(game.game_over);
+        assert_eq!(game.finish_time, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn rotating_an_o_piece_leaves_it_in_place() {
+        let mut game = Game::new();
+        assert!(game.inject_piece(Shape::O, 8, vec!['o'; 8]));
+        let before = game.current.clone().unwrap();
+        assert_eq!(before.rotated().cells(), before.cells());
+        for _ in 0..ROTATIONS {
+            assert!(!game.rotate_current());
+            game.apply_action(Action::Rotate);
+        }
+        let after = game.current.as_ref().unwrap();
+        assert_eq!((after.x, after.y, after.rotation), (before.x, before.y, before.rotation));
+    }
+
+    #[test]
+    fn preview_lead_keeps_the_queue_deep_while_runs_are_active() {
+        let mut game = game_with(|c| c.preview_lead = 4);
+        start(&mut game, 1, "make");
+        for _ in 0..6 {
+            game.hard_drop();
+            assert!(game.current.is_some());
+            assert!(game.piece_queue.len() >= 4, "queue {}", game.piece_queue.len());
+        }
+        let mut lazy = Game::new();
+        start(&mut lazy, 1, "make");
+        lazy.hard_drop();
+        assert!(lazy.piece_queue.len() < 4);
+    }
+
+    #[test]
+    fn each_top_out_rule_ends_the_game_on_its_condition() {
+        let mut spawn_row = vec![".........."; BOARD_H];
+        spawn_row[0] = "a.........";
+        let spawn_row = Board::from_ascii(&spawn_row.join("\n"), BOARD_W, BOARD_H).unwrap();
+        for (rule, on_block, on_lock) in [
+            (TopOutRule::BlockOut, true, false),
+            (TopOutRule::LockOut, false, true),
+            (TopOutRule::Both, true, true),
+        ] {
+            let config = config_with(|c| c.top_out = rule);
+            let mut blocked = game(config.clone());
+            blocked.board = full_board();
+            start(&mut blocked, 1, "cargo build");
+            assert_eq!(blocked.game_over, on_block, "{rule:?} block-out");
+
+            let mut pushed = game(config);
+            pushed.board = spawn_row.clone();
+            pushed.apply_garbage_row();
+            assert_eq!(pushed.game_over, on_lock, "{rule:?} lock-out");
+        }
+    }
+
+    #[test]
+    fn lock_resets_run_out_and_the_piece_locks_while_spinning() {
+        // Frames of spinning and shuffling on the floor before the piece locks.
+        let frames_to_lock = |cap: u32| {
+            let mut game = game_with(|c| {
+                c.lock_delay_frames = 3;
+                c.lock_reset_cap = cap;
+            });
+            assert!(game.inject_piece(Shape::T, 3, vec!['t'; 8]));
+            while game.soft_drop() {}
+            game.tick_gravity();
+            assert!(game.lock_delay.is_some());
+            (1..=40).find(|frame| {
+                let sideways = if frame % 2 == 0 { Action::Left } else { Action::Right };
+                game.apply_action(Action::Rotate);
+                game.apply_action(sideways);
+                game.process_effects();
+                game.board.cells.iter().any(|c| c.is_filled())
+            })
+        };
+        assert_eq!(frames_to_lock(1000), None);
+        let capped = frames_to_lock(4).expect("locks once the resets run out");
+        assert!(capped > 3 && capped <= 3 * 5, "locked after {capped} frames");
+    }
+
+    #[test]
+    fn a_failed_command_sets_the_failure_flash() {
+        let mut game = game_with(|c| c.fail_flash_frames = 3);
+        start(&mut game, 1, "cargo test");
+        end(&mut game, 1, 0);
+        assert_eq!(game.fail_flash_frames, 0);
+        start(&mut game, 2, "cargo test");
+        end(&mut game, 2, 101);
+        assert_eq!(game.fail_flash_frames, 3);
+        game.process_effects();
+        assert_eq!(game.fail_flash_frames, 2);
+    }
+
+    #[test]
+    fn inject_piece_places_the_shape_at_the_column() {
+        let mut game = Game::new();
+        assert!(game.inject_piece(Shape::T, 6, vec!['t'; 8]));
+        let piece = game.current.as_ref().unwrap();
+        assert_eq!(piece.shape, Shape::T);
+        assert_eq!(piece.cells().iter().map(|&(x, _, _)| x).min(), Some(6));
+        // An I lying flat can't start in the last three columns.
+        assert!(!game.inject_piece(Shape::I, 7, vec!['i'; 8]));
+        assert_eq!(game.current.as_ref().unwrap().shape, Shape::T);
+
+        let mut blocked = Game::new();
+        blocked.board = full_board();
+        assert!(!blocked.inject_piece(Shape::O, 0, vec!['o'; 8]));
+        assert!(blocked.game_over);
+    }
+
+    #[test]
+    fn an_injected_piece_requeues_the_displaced_one_as_it_was() {
+        let mut game = Game::new();
+        for (run_id, cycle, is_bomb) in [(4, 3, false), (0, 0, true)] {
+            let piece = Piece::with_payload(Shape::L, vec!['l'; 8]);
+            game.piece_queue.push_front(QueuedPiece { run_id, cycle, piece, is_bomb });
+            game.spawn_next();
+            assert!(game.inject_piece(Shape::T, 0, vec!['t'; 8]));
+            let front = &game.piece_queue[0];
+            assert_eq!((front.run_id, front.cycle, front.is_bomb), (run_id, cycle, is_bomb));
+            game.piece_queue.pop_front();
+        }
+    }
+
+    #[test]
+    fn the_shown_score_counts_up_over_the_configured_frames() {
+        let mut game = game_with(|c| c.score_count_frames = 4);
+        game.score = 800;
+        let mut shown = Vec::new();
+        for _ in 0..5 {
+            game.process_effects();
+            shown.push(game.shown_score);
+        }
+        assert_eq!(shown, [200, 400, 600, 800, 800]);
+    }
+
+    #[test]
+    fn commands_per_line_divides_commands_by_lines_cleared() {
+        let mut game = Game::new();
+        game.commands_run = 9;
+        assert_eq!(game.commands_per_line(), None);
+        game.lines_cleared = 4;
+        assert_eq!(game.commands_per_line(), Some(2.25));
+    }
+
+    #[test]
+    fn idle_pause_holds_gravity_until_a_command_starts() {
+        let mut game = game_with(|c| c.idle_pause = Some(Duration::from_secs(5)));
+        start(&mut game, 1, "cargo build");
+        let y = |game: &Game| game.current.as_ref().unwrap().y;
+        let top = y(&game);
+        game.tick_gravity();
+        assert_eq!(y(&game), top + 1);
+
+        game.advance_clock(Duration::from_secs(6));
+        assert!(game.idle_paused());
+        game.tick_gravity();
+        assert_eq!(y(&game), top + 1);
+
+        start(&mut game, 2, "cargo test");
+        assert!(!game.idle_paused());
+        game.tick_gravity();
+        assert_eq!(y(&game), top + 2);
+    }
+
+    #[test]
+    fn a_cinematic_ending_locks_the_queue_before_freezing() {
+        for ending in [Ending::Freeze, Ending::Cinematic] {
+            let mut game = game_with(|c| {
+                c.ending = ending;
+                c.time_limit = Some(Duration::from_secs(1));
+            });
+            start(&mut game, 1, "cargo build --release");
+            let queued = game.piece_queue.len();
+            assert!(queued > 0);
+            game.advance_clock(Duration::from_secs(2));
+            assert!(game.game_over);
+            for _ in 0..(queued + 1) * BOARD_H * 2 {
+                game.tick_gravity();
+                game.process_effects();
+            }
+            assert!(game.frozen(), "{ending:?}");
+            let filled = game.board.cells.iter().filter(|c| c.is_filled()).count();
+            if ending == Ending::Cinematic {
+                assert!(game.piece_queue.is_empty() && game.current.is_none());
+                assert_eq!(filled, 4 * (queued + 1));
+            } else {
+                assert_eq!(game.piece_queue.len(), queued);
+                assert_eq!(filled, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn the_first_command_grace_only_covers_the_first_spawn() {
+        let mut game = game_with(|c| c.first_spawn_grace_frames = 5);
+        start(&mut game, 1, "cargo build --release");
+        assert_eq!(game.spawn_grace, 5);
+        let top = game.current.as_ref().unwrap().y;
+        game.tick_gravity();
+        assert_eq!(game.current.as_ref().unwrap().y, top);
+        game.hard_drop();
+        assert!(game.current.is_some());
+        assert_eq!(game.spawn_grace, 0);
+    }
+
+    #[test]
+    fn clears_feed_the_variety_meter_when_configured() {
+        for (points, meter) in [(0, 0), (20, 20)] {
+            let mut game = game_with(|c| c.clear_variety = points);
+            game.board = Board::from_ascii("....######", BOARD_W, BOARD_H).unwrap();
+            assert!(game.inject_piece(Shape::I, 0, vec!['i'; 8]));
+            game.hard_drop();
+            while !game.pending_clear.is_empty() {
+                game.process_effects();
+            }
+            assert_eq!(game.lines_cleared, 1);
+            assert_eq!(game.variety_meter, meter);
+        }
+    }
+
+    #[test]
+    fn an_action_command_hard_drops_instead_of_queueing_pieces() {
+        let mut game = game_with(|c| c.command_actions = [("drop".to_string(), Action::HardDrop)].into_iter().collect());
+        start(&mut game, 1, "cargo build --release");
+        assert!(game.board.cells.iter().all(|&c| c == Cell::Empty));
+        let commands = game.commands_run;
+        start(&mut game, 2, "drop");
+        assert_eq!(game.board.cells.iter().filter(|c| c.is_filled()).count(), 4);
+        assert!(game.piece_queue.iter().all(|qp| qp.run_id == 1));
+        assert_eq!(game.commands_run, commands);
+        end(&mut game, 2, 1);
+        assert_eq!(game.fail_streak, 0);
+    }
+
+    #[test]
+    fn identical_starts_within_the_merge_window_share_one_run() {
+        let mut game = game_with(|c| c.merge_window = Some(Duration::from_secs(5)));
+        start(&mut game, 1, "cargo test");
+        game.advance_clock(Duration::from_secs(1));
+        start(&mut game, 2, "cargo test");
+        assert_eq!(game.active_runs.keys().collect::<Vec<_>>(), [&1]);
+        assert!(game.piece_queue.iter().all(|qp| qp.run_id == 1));
+
+        end(&mut game, 2, 0);
+        assert!(game.active_runs[&1].active);
+        end(&mut game, 1, 0);
+        assert!(!game.active_runs.get(&1).is_some_and(|run| run.active));
+
+        game.advance_clock(Duration::from_secs(10));
+        start(&mut game, 3, "cargo test");
+        assert!(game.active_runs.contains_key(&3));
+    }
+
+    #[test]
+    fn a_cancel_mapped_to_none_is_no_failure() {
+        let outcome = |code: i32| {
+            let config = config_from(&[("STACK_EXIT_EFFECTS", "1=garbage,130=none"), ("STACK_FAILURE_VARIETY", "half")]);
+            let mut game = game(config.unwrap());
+            start(&mut game, 1, "make");
+            end(&mut game, 1, 0);
+            start(&mut game, 2, "cargo test");
+            end(&mut game, 2, code);
+            let garbage = game.board.cells.iter().filter(|&&c| c == Cell::Garbage).count();
+            (garbage, game.fail_streak, game.failure_digest(1).len(), game.success_rate(), game.variety_meter)
+        };
+        let (_, _, _, _, full_variety) = outcome(0);
+        assert_eq!(outcome(130), (0, 0, 0, Some(100), full_variety));
+        let (garbage, streak, failures, rate, variety) = outcome(1);
+        assert_eq!((garbage, streak, failures, rate), (BOARD_W - 1, 1, 1, Some(50)));
+        assert!(variety < full_variety);
+    }
 }
@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+
+// Finished commands the success gauge looks back over.
+const SUCCESS_WINDOW: usize = 20;
+
+#[derive(Default)]
+pub struct SuccessRate {
+    recent: VecDeque<bool>,
+}
+
+impl SuccessRate {
+    pub fn record(&mut self, succeeded: bool) {
+        if self.recent.len() == SUCCESS_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(succeeded);
+    }
+
+    // Share of the window that succeeded, rounded down to a whole percent;
+    // `None` until a command has finished.
+    pub fn percent(&self) -> Option<u32> {
+        if self.recent.is_empty() {
+            return None;
+        }
+        let ok = self.recent.iter().filter(|&&s| s).count();
+        Some((ok * 100 / self.recent.len()) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_covers_the_recent_window() {
+        let mut rate = SuccessRate::default();
+        assert_eq!(rate.percent(), None);
+        for succeeded in [true, true, false] {
+            rate.record(succeeded);
+        }
+        assert_eq!(rate.percent(), Some(66));
+        // Twenty successes push the early failure out of the window.
+        for _ in 0..SUCCESS_WINDOW {
+            rate.record(true);
+        }
+        assert_eq!(rate.percent(), Some(100));
+        for _ in 0..5 {
+            rate.record(false);
+        }
+        assert_eq!(rate.percent(), Some(75));
+    }
+}
@@ -0,0 +1,177 @@
+use std::error::Error;
+use std::io::{self, stdin, BufRead, BufWriter, Write};
+use std::os::unix::net::UnixStream;
+
+use super::socket::{parse_command_line, MAX_COMMAND_BYTES};
+use crate::CommandEvent;
+
+// Compact binary framing for integrations that fire many events, read
+// alongside the text protocol on the same socket:
+//
+//   0xF1 <varint id> <varint len> <len bytes of command>          START
+//   0xF2 <varint id> <varint zigzag exit code> <flags>            END
+//        [<varint stderr lines> if flags & 1] [<varint ms> if flags & 2]
+//
+// Varints are LEB128. The tag bytes can't begin a text line, so the reader
+// tells the two apart message by message without a handshake.
+const TAG_START: u8 = 0xF1;
+const TAG_END: u8 = 0xF2;
+
+const HAS_STDERR: u8 = 1;
+const HAS_DURATION: u8 = 2;
+
+pub fn is_frame_tag(byte: u8) -> bool {
+    byte == TAG_START || byte == TAG_END
+}
+
+pub fn encode_event(ev: &CommandEvent) -> Vec<u8> {
+    let mut out = Vec::new();
+    match ev {
+        CommandEvent::Start { id, command } => {
+            out.push(TAG_START);
+            put_varint(&mut out, *id);
+            put_varint(&mut out, command.len() as u64);
+            out.extend_from_slice(command.as_bytes());
+        }
+        CommandEvent::End {
+            id,
+            exit_code,
+            stderr_lines,
+            duration_ms,
+        } => {
+            out.push(TAG_END);
+            put_varint(&mut out, *id);
+            put_varint(&mut out, zigzag(*exit_code));
+            let mut flags = 0;
+            if stderr_lines.is_some() {
+                flags |= HAS_STDERR;
+            }
+            if duration_ms.is_some() {
+                flags |= HAS_DURATION;
+            }
+            out.push(flags);
+            for value in [stderr_lines, duration_ms].into_iter().flatten() {
+                put_varint(&mut out, *value);
+            }
+        }
+    }
+    out
+}
+
+pub fn read_event<R: BufRead>(reader: &mut R) -> io::Result<CommandEvent> {
+    match read_byte(reader)? {
+        TAG_START => {
+            let id = read_varint(reader)?;
+            let len = read_varint(reader)? as usize;
+            if len > MAX_COMMAND_BYTES {
+                return Err(invalid(format!("command of {len} bytes is too long")));
+            }
+            let mut command = vec![0; len];
+            reader.read_exact(&mut command)?;
+            let command = String::from_utf8(command).map_err(|_| invalid("command is not UTF-8".into()))?;
+            Ok(CommandEvent::Start { id, command })
+        }
+        TAG_END => {
+            let id = read_varint(reader)?;
+            let code = unzigzag(read_varint(reader)?).ok_or_else(|| invalid("exit code out of range".into()))?;
+            let flags = read_byte(reader)?;
+            let stderr_lines = if flags & HAS_STDERR != 0 { Some(read_varint(reader)?) } else { None };
+            let duration_ms = if flags & HAS_DURATION != 0 { Some(read_varint(reader)?) } else { None };
+            Ok(CommandEvent::End {
+                id,
+                exit_code: code,
+                stderr_lines,
+                duration_ms,
+            })
+        }
+        other => Err(invalid(format!("unknown frame tag {other:#04x}"))),
+    }
+}
+
+// `stack-game pipe`: forward START/END lines from stdin to the running game
+// as binary frames over one connection, for build systems that emit events
+// in bulk. Lines that aren't events are skipped.
+pub fn run_pipe(path: &str) -> Result<(), Box<dyn Error>> {
+    let stream = UnixStream::connect(path).map_err(|e| format!("cannot connect to {path}: {e}"))?;
+    let mut out = BufWriter::new(stream);
+    for line in stdin().lock().lines() {
+        if let Some(ev) = parse_command_line(&line?) {
+            out.write_all(&encode_event(&ev))?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint<R: BufRead>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(reader)?;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint longer than 64 bits".into()))
+}
+
+fn read_byte<R: BufRead>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn zigzag(value: i32) -> u64 {
+    u64::from(((value << 1) ^ (value >> 31)) as u32)
+}
+
+fn unzigzag(value: u64) -> Option<i32> {
+    let value = u32::try_from(value).ok()?;
+    Some(((value >> 1) as i32) ^ -((value & 1) as i32))
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_and_end_frames_round_trip() {
+        let events = [
+            CommandEvent::Start {
+                id: 300,
+                command: "make -j8 \"all\"\nécho ✓".to_string(),
+            },
+            CommandEvent::End {
+                id: 300,
+                exit_code: -2,
+                stderr_lines: Some(1 << 40),
+                duration_ms: None,
+            },
+            CommandEvent::End {
+                id: 1,
+                exit_code: i32::MAX,
+                stderr_lines: None,
+                duration_ms: Some(42),
+            },
+        ];
+        let bytes: Vec<u8> = events.iter().flat_map(encode_event).collect();
+        let mut reader = &bytes[..];
+        for ev in &events {
+            assert!(is_frame_tag(reader[0]));
+            assert_eq!(format!("{:?}", read_event(&mut reader).unwrap()), format!("{ev:?}"));
+        }
+        assert!(reader.is_empty());
+    }
+}
@@ -1,3 +1,6 @@
+mod frame;
 mod socket;
 
-pub use socket::spawn_socket_listener;
+pub use frame::run_pipe;
+
+pub use socket::{answer_query, bind_socket, spawn_socket_listener, SocketMessage};
@@ -1,38 +1,195 @@
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::sync::mpsc;
 use std::thread;
+use std::time::Duration;
 
-use crate::{CommandEvent, SOCKET_PATH};
+use log::debug;
 
-pub fn spawn_socket_listener(tx: mpsc::Sender<CommandEvent>) {
-    let _ = fs::remove_file(SOCKET_PATH);
-    let listener = UnixListener::bind(SOCKET_PATH).ok();
+use super::frame::{is_frame_tag, read_event};
+use crate::game::{encode_snapshot, Input};
+use crate::{CommandEvent, Game};
+
+const PING_TIMEOUT: Duration = Duration::from_millis(500);
+pub(super) const MAX_COMMAND_BYTES: usize = 64 * 1024;
+
+pub enum SocketMessage {
+    Event(CommandEvent),
+    Query(Query, mpsc::Sender<String>),
+    // `INJECT <shape> <col> [text]`, applied only when debug commands are on.
+    Debug(Input),
+}
+
+#[derive(Debug)]
+pub enum Query {
+    // `SCORE` -> `{"score":N,"lines":N,"level":N}`, cheap enough to poll per prompt.
+    Score,
+    // `SNAPSHOT` -> the compact board snapshot, loadable via `STACK_SNAPSHOT`.
+    Snapshot,
+}
+
+pub fn answer_query(query: &Query, game: &Game) -> String {
+    match query {
+        Query::Score => {
+            let snap = game.score_snapshot();
+            format!(
+                "{{\"score\":{},\"lines\":{},\"level\":{}}}",
+                snap.score, snap.lines, snap.level
+            )
+        }
+        Query::Snapshot => encode_snapshot(&game.snapshot()),
+    }
+}
+
+// Bind the game socket. A leftover file from a crashed game is removed and
+// rebound; one that still accepts connections belongs to someone else.
+pub fn bind_socket(path: &str) -> Result<UnixListener, String> {
+    match UnixListener::bind(path) {
+        Ok(listener) => return Ok(listener),
+        Err(e) if e.kind() != io::ErrorKind::AddrInUse => return Err(format!("cannot bind {path}: {e}")),
+        Err(_) => {}
+    }
+    if let Ok(stream) = UnixStream::connect(path) {
+        return Err(if answers_ping(stream) {
+            format!("another waitris is already running (socket {path})")
+        } else {
+            format!("{path} is in use by another program")
+        });
+    }
+    debug!("removing stale socket {path}");
+    fs::remove_file(path).map_err(|e| format!("cannot remove stale socket {path}: {e}"))?;
+    UnixListener::bind(path).map_err(|e| format!("cannot bind {path}: {e}"))
+}
+
+fn answers_ping(mut stream: UnixStream) -> bool {
+    let _ = stream.set_read_timeout(Some(PING_TIMEOUT));
+    if writeln!(stream, "PING").is_err() {
+        return false;
+    }
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).is_ok() && reply.trim() == "PONG"
+}
+
+pub fn spawn_socket_listener(listener: UnixListener, tx: mpsc::Sender<SocketMessage>) {
     thread::spawn(move || {
-        if let Some(listener) = listener {
-            for stream in listener.incoming() {
-                if let Ok(stream) = stream {
-                    handle_stream(stream, &tx);
-                }
-            }
+        for stream in listener.incoming().flatten() {
+            handle_stream(stream, &tx);
         }
     });
 }
 
-fn handle_stream(stream: UnixStream, tx: &mpsc::Sender<CommandEvent>) {
-    let reader = BufReader::new(stream);
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            if let Some(ev) = parse_command_line(&line) {
-                let _ = tx.send(ev);
+fn handle_stream(stream: UnixStream, tx: &mpsc::Sender<SocketMessage>) {
+    let mut writer = stream.try_clone().ok();
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+    loop {
+        match reader.fill_buf() {
+            Ok([]) | Err(_) => return,
+            Ok(&[byte, ..]) if is_frame_tag(byte) => match read_event(&mut reader) {
+                Ok(ev) => {
+                    let _ = tx.send(SocketMessage::Event(ev));
+                    continue;
+                }
+                Err(e) => {
+                    debug!("dropping connection on a bad frame: {e}");
+                    return;
+                }
+            },
+            Ok(_) => {}
+        }
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        let Ok(line) = std::str::from_utf8(&buf) else {
+            debug!("skipping a line that is not valid UTF-8");
+            continue;
+        };
+        // Liveness probe from a second instance; answered without the game loop.
+        if line.trim() == "PING" {
+            if let Some(w) = writer.as_mut() {
+                let _ = writeln!(w, "PONG");
             }
+        } else if let Some(query) = parse_query_line(line) {
+            let (reply_tx, reply_rx) = mpsc::channel();
+            if tx.send(SocketMessage::Query(query, reply_tx)).is_err() {
+                return;
+            }
+            if let (Ok(reply), Some(w)) = (reply_rx.recv_timeout(Duration::from_secs(1)), writer.as_mut()) {
+                let _ = writeln!(w, "{reply}");
+            }
+        } else if let Some((id, len)) = parse_sized_start(line) {
+            let mut payload = vec![0; len];
+            if reader.read_exact(&mut payload).is_err() {
+                return;
+            }
+            if let Some(ev) = exact_start("STARTN", id, payload) {
+                let _ = tx.send(SocketMessage::Event(ev));
+            }
+        } else if let Some(args) = line.trim_end_matches(['\r', '\n']).strip_prefix("INJECT ") {
+            match Input::parse_inject(args) {
+                Ok(input) => {
+                    let _ = tx.send(SocketMessage::Debug(input));
+                }
+                Err(e) => debug!("bad INJECT: {e}"),
+            }
+        } else if let Some(ev) = parse_command_line(line) {
+            let _ = tx.send(SocketMessage::Event(ev));
+        }
+    }
+}
+
+// `STARTN <id> <bytes>`: the command follows as exactly that many raw bytes,
+// so hooks can send quotes and newlines without escaping anything.
+fn parse_sized_start(line: &str) -> Option<(u64, usize)> {
+    let mut parts = line.trim().strip_prefix("STARTN ")?.split_whitespace();
+    let id = parts.next()?.parse().ok()?;
+    let len = parts.next()?.parse().ok().filter(|&n| n <= MAX_COMMAND_BYTES)?;
+    Some((id, len))
+}
+
+// `STARTF <id> <path>`: the command is the contents of a file the hook wrote.
+// The hook owns the file and removes it; the game only reads it.
+fn read_command_file(rest: &str) -> Option<CommandEvent> {
+    let (id, path) = rest.split_once(' ')?;
+    let id = id.parse().ok()?;
+    let file = fs::File::open(path.trim()).ok()?;
+    let mut bytes = Vec::new();
+    file.take(MAX_COMMAND_BYTES as u64 + 1).read_to_end(&mut bytes).ok()?;
+    if bytes.len() > MAX_COMMAND_BYTES {
+        debug!("dropping STARTF {id}: command is over {MAX_COMMAND_BYTES} bytes");
+        return None;
+    }
+    exact_start("STARTF", id, bytes)
+}
+
+// The bytes are the command exactly; one that isn't text is dropped rather
+// than altered.
+fn exact_start(kind: &str, id: u64, bytes: Vec<u8>) -> Option<CommandEvent> {
+    match String::from_utf8(bytes) {
+        Ok(command) => Some(CommandEvent::Start { id, command }),
+        Err(_) => {
+            debug!("dropping {kind} {id}: command is not valid UTF-8");
+            None
         }
     }
 }
 
-fn parse_command_line(line: &str) -> Option<CommandEvent> {
+fn parse_query_line(line: &str) -> Option<Query> {
+    match line.trim() {
+        "SCORE" => Some(Query::Score),
+        "SNAPSHOT" => Some(Query::Snapshot),
+        _ => None,
+    }
+}
+
+pub(super) fn parse_command_line(line: &str) -> Option<CommandEvent> {
     let line = line.trim();
+    if let Some(rest) = line.strip_prefix("STARTF ") {
+        return read_command_file(rest);
+    }
     if let Some(rest) = line.strip_prefix("START ") {
         let mut parts = rest.splitn(2, ' ');
         let id_str = parts.next()?;
@@ -49,7 +206,107 @@ fn parse_command_line(line: &str) -> Option<CommandEvent> {
         let code_str = parts.next().unwrap_or("0");
         let id = id_str.parse().ok()?;
         let exit_code = code_str.parse().unwrap_or(0);
-        return Some(CommandEvent::End { id, _exit_code: exit_code });
+        // Optional `key=value` fields follow; unknown ones are skipped so
+        // newer hooks still work with older games.
+        let (mut stderr_lines, mut duration_ms) = (None, None);
+        for field in parts {
+            if let Some(n) = field.strip_prefix("stderr=") {
+                stderr_lines = n.parse().ok();
+            } else if let Some(n) = field.strip_prefix("ms=") {
+                duration_ms = n.parse().ok();
+            }
+        }
+        return Some(CommandEvent::End {
+            id,
+            exit_code,
+            stderr_lines,
+            duration_ms,
+        });
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_query_returns_exactly_score_lines_and_level() {
+        let mut game = Game::new();
+        game.score = 1200;
+        game.lines_cleared = 12;
+        let query = parse_query_line("SCORE\n").unwrap();
+        let reply = answer_query(&query, &game);
+        assert_eq!(reply, r#"{"score":1200,"lines":12,"level":2}"#);
+        let body = reply.trim_start_matches('{').trim_end_matches('}');
+        let keys: Vec<&str> = body.split(',').filter_map(|field| field.split(':').next()).collect();
+        assert_eq!(keys, [r#""score""#, r#""lines""#, r#""level""#]);
+    }
+
+    #[test]
+    fn stale_socket_file_is_removed_and_rebound() {
+        let path = std::env::temp_dir().join(format!("stack-game-sock-test-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        drop(UnixListener::bind(path).unwrap());
+        assert!(fs::metadata(path).is_ok());
+        let listener = bind_socket(path).unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        spawn_socket_listener(listener, tx);
+        let err = bind_socket(path).unwrap_err();
+        assert!(err.contains("already running"), "{err}");
+        let _ = fs::remove_file(path);
+    }
+
+    fn events_from(bytes: &[u8]) -> Vec<CommandEvent> {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.write_all(bytes).unwrap();
+        drop(client);
+        let (tx, rx) = mpsc::channel();
+        handle_stream(server, &tx);
+        drop(tx);
+        rx.iter()
+            .filter_map(|msg| match msg {
+                SocketMessage::Event(ev) => Some(ev),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_line_that_is_not_text_is_skipped_without_closing_the_connection() {
+        let events = events_from(b"START 1 ls\n\xffEND 1 0\nEND 1 2\n");
+        assert_eq!(events.len(), 2, "{events:?}");
+        assert!(matches!(events[0], CommandEvent::Start { id: 1, .. }));
+        assert!(matches!(events[1], CommandEvent::End { id: 1, exit_code: 2, .. }));
+    }
+
+    #[test]
+    fn file_start_keeps_the_command_byte_for_byte() {
+        let path = std::env::temp_dir().join(format!("stack-game-startf-test-{}", std::process::id()));
+        let command = "  printf 'a\\n'\n";
+        fs::write(&path, command).unwrap();
+        match parse_command_line(&format!("STARTF 3 {}\n", path.display())) {
+            Some(CommandEvent::Start { id: 3, command: read }) => assert_eq!(read, command),
+            other => panic!("unexpected {other:?}"),
+        }
+        fs::write(&path, b"echo \xff").unwrap();
+        assert!(parse_command_line(&format!("STARTF 4 {}", path.display())).is_none());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn sized_start_keeps_the_command_byte_for_byte() {
+        let command = "  git commit -m \"it's \\\"done\\\"\"\necho 'two'  ";
+        let mut bytes = format!("STARTN 5 {}\n{command}", command.len()).into_bytes();
+        bytes.extend_from_slice(b"STARTN 6 2\n\xff\xfeEND 5 0\n");
+        let events = events_from(&bytes);
+        assert_eq!(events.len(), 2, "{events:?}");
+        match &events[0] {
+            CommandEvent::Start { id: 5, command: sent } => assert_eq!(sent, command),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(events[1], CommandEvent::End { id: 5, exit_code: 0, .. }));
+    }
+}
@@ -0,0 +1,138 @@
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+pub const DEFAULT_LOG_PATH: &str = "/tmp/stack-game.log";
+
+// Diagnostic logger. Writes to a file (the TUI owns the terminal) unless
+// `STACK_LOG_FILE=stderr`. With no level configured the logger is never
+// installed and `log`'s max level stays `Off`, so disabled call sites cost a
+// single comparison.
+struct Logger {
+    started: Instant,
+    sink: Mutex<Sink>,
+}
+
+enum Sink {
+    File(File),
+    Stderr,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{:>8}ms {:<5} {}: {}\n",
+            self.started.elapsed().as_millis(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if let Ok(mut sink) = self.sink.lock() {
+            let _ = match &mut *sink {
+                Sink::File(file) => file.write_all(line.as_bytes()),
+                Sink::Stderr => io::stderr().write_all(line.as_bytes()),
+            };
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut sink) = self.sink.lock()
+            && let Sink::File(file) = &mut *sink
+        {
+            let _ = file.flush();
+        }
+    }
+}
+
+// `--verbose` means debug; otherwise `STACK_LOG` or `RUST_LOG` pick the level.
+pub fn init(verbose: bool) -> Result<(), String> {
+    let level = if verbose {
+        LevelFilter::Debug
+    } else {
+        match env::var("STACK_LOG").or_else(|_| env::var("RUST_LOG")) {
+            Ok(spec) => parse_level(&spec)?,
+            Err(_) => LevelFilter::Off,
+        }
+    };
+    if level == LevelFilter::Off {
+        return Ok(());
+    }
+
+    let path = env::var("STACK_LOG_FILE").unwrap_or_else(|_| DEFAULT_LOG_PATH.to_string());
+    let sink = if path == "stderr" {
+        Sink::Stderr
+    } else {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("cannot open log file {path}: {e}"))?;
+        Sink::File(file)
+    };
+    let logger = Logger {
+        started: Instant::now(),
+        sink: Mutex::new(sink),
+    };
+    log::set_logger(Box::leak(Box::new(logger))).map_err(|e| e.to_string())?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+// Accepts a bare level (`debug`) or `RUST_LOG`-style `target=level` items;
+// the most verbose level mentioned wins.
+fn parse_level(spec: &str) -> Result<LevelFilter, String> {
+    let mut level = LevelFilter::Off;
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let name = item.rsplit('=').next().unwrap_or(item);
+        let parsed: LevelFilter = name
+            .parse()
+            .map_err(|_| format!("unknown log level `{name}`"))?;
+        level = level.max(parsed);
+    }
+    Ok(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommandEvent, Game};
+
+    #[test]
+    fn parse_level_takes_the_most_verbose_item() {
+        assert_eq!(parse_level("warn").unwrap(), LevelFilter::Warn);
+        assert_eq!(parse_level("info,stack_game=trace").unwrap(), LevelFilter::Trace);
+        assert!(parse_level("loud").is_err());
+    }
+
+    #[test]
+    fn enabled_logging_captures_a_command_start() {
+        let path = env::temp_dir().join(format!("stack-game-log-test-{}.log", std::process::id()));
+        let file = File::create(&path).unwrap();
+        let logger = Logger {
+            started: Instant::now(),
+            sink: Mutex::new(Sink::File(file)),
+        };
+        log::set_logger(Box::leak(Box::new(logger))).unwrap();
+        log::set_max_level(LevelFilter::Debug);
+        let mut game = Game::new();
+        game.handle_command_event(CommandEvent::Start {
+            id: 4242,
+            command: "cargo build".to_string(),
+        });
+        log::logger().flush();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(text.lines().any(|line| line.contains("DEBUG") && line.contains("start 4242 `cargo build`")));
+    }
+}
@@ -1,17 +1,56 @@
+use std::env;
 use std::error::Error;
 
 mod app;
 mod config;
 mod game;
 mod io;
+mod logging;
+mod replay;
 mod ui;
 mod commands;
+mod project;
+#[cfg(test)]
+mod testing;
 pub use game::{CommandEvent, Game};
 pub use config::{
-    BOMB_CAP, BOARD_H, BOARD_W, CELL_W, CHUNK_SIZE, MIN_PANE_WIDTH, PLAY_H, PLAY_W, SOCKET_PATH,
+    Config, BOMB_CAP, BOARD_H, BOARD_W, CELL_W, CHUNK_SIZE, GRAVITY_MS, LINES_PER_LEVEL, MIN_PANE_WIDTH, PANEL_EXPAND, PLAY_H, PLAY_W, SOCKET_PATH,
     VARIETY_THRESH,
 };
 
 fn main() -> Result<(), Box<dyn Error>> {
-    app::run()
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("replay") {
+        return replay::run_cli(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("validate") {
+        return replay::validate::run_cli(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("diff") {
+        return replay::diff::run_cli(&args[1..]);
+    }
+    let verbose = args.iter().any(|arg| arg == "--verbose" || arg == "-v");
+    logging::init(verbose)?;
+    let mut config = Config::from_env()?;
+    if let Some(name) = project::project_arg(&args) {
+        config.socket_path = project::socket_path(Some(name));
+    }
+    if args.first().map(String::as_str) == Some("pipe") {
+        return io::run_pipe(&config.socket_path);
+    }
+    if args.iter().any(|arg| arg == "--ironman") {
+        config.ironman = true;
+        config.settings.insert("STACK_IRONMAN".to_string(), "1".to_string());
+    }
+    if let Some(pos) = args.iter().position(|arg| arg == "--practice") {
+        let name = args.get(pos + 1).ok_or("usage: stack-game --practice <scenario>")?;
+        config.scenario = Some(game::load_scenario(&config.scenario_dir, name)?);
+        config.settings.insert("STACK_SCENARIO".to_string(), name.clone());
+    }
+    if let Some(pos) = args.iter().position(|arg| arg == "--script") {
+        let path = args.get(pos + 1).ok_or("usage: stack-game --script <file> [--render]")?;
+        let render = args.iter().any(|arg| arg == "--render");
+        return replay::script::run_file(path, config, render);
+    }
+    app::run(config)
 }
@@ -0,0 +1,40 @@
+// Socket and tmux session names for per-project games. Both binaries build
+// this file (`waitris` through `#[path]`), so the launcher, the CLI and the
+// game always agree on where a project's game listens.
+
+pub const DEFAULT_SOCKET: &str = "/tmp/stack-game.sock";
+
+// `foo` -> `/tmp/stack-game-foo.sock`; no project is the shared default.
+pub fn socket_path(project: Option<&str>) -> String {
+    match project {
+        Some(name) => format!("/tmp/stack-game-{}.sock", sanitize(name)),
+        None => DEFAULT_SOCKET.to_string(),
+    }
+}
+
+// Keep names to one path component tmux accepts as a target.
+pub fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect()
+}
+
+// `--project <name>` anywhere in the arguments.
+pub fn project_arg(args: &[String]) -> Option<&str> {
+    let pos = args.iter().position(|arg| arg == "--project")?;
+    args.get(pos + 1).map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_project_name_picks_its_own_socket() {
+        assert_eq!(socket_path(Some("foo")), "/tmp/stack-game-foo.sock");
+        assert_eq!(socket_path(Some("my app/v2")), "/tmp/stack-game-my-app-v2.sock");
+        assert_eq!(socket_path(None), DEFAULT_SOCKET);
+        let args: Vec<String> = ["attach", "--project", "foo"].map(String::from).to_vec();
+        assert_eq!(socket_path(project_arg(&args)), socket_path(Some("foo")));
+    }
+}
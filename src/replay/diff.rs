@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fs;
+
+use crate::game::Board;
+use crate::replay::log::{format_input, parse_log, EventLog};
+use crate::replay::Player;
+
+const DIFF_USAGE: &str = "usage: stack-game diff <log1> <log2>";
+
+// Where two replays part ways and how far apart they end up.
+pub struct LogDiff {
+    // 1-based index of the first event after which the boards' cells differ,
+    // with both boards at that point. Which run placed a cell doesn't count.
+    pub divergence: Option<(usize, Board, Board)>,
+    pub scores: (u64, u64),
+    pub lines: (u64, u64),
+    pub events: (usize, usize),
+}
+
+// Replay both logs side by side, each with its own recorded config, comparing
+// boards after every event. The shorter log's game simply stops while the
+// longer one plays on.
+pub fn diff_logs(a: &EventLog, b: &EventLog) -> Result<LogDiff, String> {
+    let mut left = Player::new(a, a.config()?);
+    let mut right = Player::new(b, b.config()?);
+    let mut divergence = None;
+    let mut index = 0;
+    loop {
+        let stepped = left.step().is_some() | right.step().is_some();
+        if !stepped {
+            break;
+        }
+        index += 1;
+        if divergence.is_none() && left.game.board.cells != right.game.board.cells {
+            divergence = Some((index, left.game.board.clone(), right.game.board.clone()));
+        }
+    }
+    Ok(LogDiff {
+        divergence,
+        scores: (left.game.score, right.game.score),
+        lines: (left.game.lines_cleared, right.game.lines_cleared),
+        events: (a.entries.len(), b.entries.len()),
+    })
+}
+
+pub fn run_cli(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [path_a, path_b] = args else {
+        return Err(DIFF_USAGE.into());
+    };
+    let (a, b) = (read_log(path_a)?, read_log(path_b)?);
+    let diff = diff_logs(&a, &b)?;
+    match &diff.divergence {
+        None => println!("boards never diverge over {} events", diff.events.0.max(diff.events.1)),
+        Some((index, board_a, board_b)) => {
+            println!("boards first differ after event {index}:");
+            for (path, log) in [(path_a, &a), (path_b, &b)] {
+                let event = log.entries.get(index - 1).map_or("(log ended)".to_string(), |e| format_input(&e.input));
+                println!("  {path}: {event}");
+            }
+            let rows_a = board_a.to_ascii();
+            let rows_b = board_b.to_ascii();
+            println!("{:<width$}   {}", path_a, path_b, width = board_a.width);
+            for (row_a, row_b) in rows_a.iter().zip(&rows_b) {
+                let marker = if row_a == row_b { ' ' } else { '*' };
+                println!("{row_a} {marker} {row_b}");
+            }
+        }
+    }
+    let (score_a, score_b) = diff.scores;
+    let (lines_a, lines_b) = diff.lines;
+    println!("score {score_a} -> {score_b} ({:+})", score_b as i64 - score_a as i64);
+    println!("lines {lines_a} -> {lines_b} ({:+})", lines_b as i64 - lines_a as i64);
+    Ok(())
+}
+
+fn read_log(path: &str) -> Result<EventLog, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    parse_log(&text).map_err(|e| format!("{path}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: u64, shift: &str) -> EventLog {
+        parse_log(&format!(
+            "# waitris event log v2
+SEED 9
+0 START {id} cargo build
+16 FRAME 16667
+20 KEY {shift}
+33 KEY hard-drop
+50 FRAME 16667
+"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn diff_finds_the_first_event_the_boards_differ_after() {
+        let same = diff_logs(&session(1, "left"), &session(1, "left")).unwrap();
+        assert!(same.divergence.is_none());
+        assert_eq!(same.scores.0, same.scores.1);
+
+        let apart = diff_logs(&session(1, "left"), &session(1, "right")).unwrap();
+        let (index, left, right) = apart.divergence.unwrap();
+        assert_eq!(index, 4);
+        assert_ne!(left, right);
+        assert_eq!(apart.events, (5, 5));
+    }
+
+    #[test]
+    fn run_ids_alone_are_not_a_divergence() {
+        let renumbered = diff_logs(&session(1, "left"), &session(7, "left")).unwrap();
+        assert!(renumbered.divergence.is_none());
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::replay::log::EventLog;
+use crate::replay::Player;
+use crate::ui::{buffer_lines, render_to_buffer, required_height, required_width, View};
+use crate::Config;
+use crate::game::Input;
+
+// Big enough for the full cabinet at the config's cell width: the well plus
+// info and controls boxes, with every optional INFO row showing.
+pub fn export_size(config: &Config) -> (u16, u16) {
+    (config.min_pane_width.max(required_width(config.cell_w)), required_height())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    // asciinema v2 cast: a JSON header line, then `[seconds, "o", data]` events.
+    Cast,
+    // Plain ANSI movie: a `WAITRIS-MOVIE` header, then `@<seconds>` before each frame.
+    Ansi,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cast" => Some(ExportFormat::Cast),
+            "ansi" => Some(ExportFormat::Ansi),
+            _ => None,
+        }
+    }
+}
+
+pub struct Frame {
+    pub at: Duration,
+    pub lines: Vec<String>,
+}
+
+// Replay the log and capture a frame at each render pass, the same point the
+// live loop draws. Consecutive identical screens collapse into one frame.
+pub fn render_frames(log: &EventLog, config: Config) -> Vec<Frame> {
+    let view = View::default();
+    let (width, height) = export_size(&config);
+    let mut player = Player::new(log, config);
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut push = |at: Duration, game: &crate::Game| {
+        let lines = buffer_lines(&render_to_buffer(game, &view, width, height), true);
+        if frames.last().is_none_or(|f| f.lines != lines) {
+            frames.push(Frame { at, lines });
+        }
+    };
+    while let Some(entry) = player.peek() {
+        let at = entry.at;
+        if let Input::Frame(_) = entry.input {
+            push(at, &player.game);
+        }
+        player.step();
+    }
+    let end = log.entries.last().map(|e| e.at).unwrap_or_default();
+    push(end, &player.game);
+    frames
+}
+
+pub fn write_export(frames: &[Frame], (width, height): (u16, u16), format: ExportFormat, out: &mut impl Write) -> io::Result<()> {
+    match format {
+        ExportFormat::Cast => {
+            writeln!(out, "{{\"version\": 2, \"width\": {width}, \"height\": {height}}}")?;
+            for (i, frame) in frames.iter().enumerate() {
+                let clear = if i == 0 { "\x1b[2J" } else { "" };
+                let data = format!("{clear}\x1b[H{}", frame.lines.join("\r\n"));
+                writeln!(out, "[{:.3}, \"o\", {}]", frame.at.as_secs_f64(), json_string(&data))?;
+            }
+        }
+        ExportFormat::Ansi => {
+            writeln!(out, "WAITRIS-MOVIE 1 {width}x{height}")?;
+            for frame in frames {
+                writeln!(out, "@{:.3}", frame.at.as_secs_f64())?;
+                for line in &frame.lines {
+                    writeln!(out, "{line}")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::log::parse_log;
+    use crate::testing::*;
+    use crate::CommandEvent;
+
+    const SHORT_LOG: &str = "# waitris event log v2
+SEED 9
+SET STACK_SPAWN_ROTATION=T=1,I=1
+0 START 1 cargo build
+16 FRAME 16667
+33 KEY hard-drop
+50 FRAME 16667
+60 END 1 0
+66 FRAME 16667
+";
+
+    #[test]
+    fn exporting_a_short_replay_writes_a_well_formed_cast() {
+        let log = parse_log(SHORT_LOG).unwrap();
+        let config = log.config().unwrap();
+        assert_eq!(config.spawn_rotation[crate::game::Shape::T.index()], 1);
+        let size = export_size(&config);
+        let frames = render_frames(&log, config);
+        let mut out = Vec::new();
+        write_export(&frames, size, ExportFormat::Cast, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(format!("{{\"version\": 2, \"width\": 40, \"height\": {}}}", required_height()).as_str()));
+        let mut last = -1.0;
+        let mut events = 0;
+        for line in lines {
+            let body = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')).unwrap();
+            let (at, rest) = body.split_once(", ").unwrap();
+            let at: f64 = at.parse().unwrap();
+            assert!(at >= last);
+            last = at;
+            let data = rest.strip_prefix("\"o\", \"").and_then(|d| d.strip_suffix('"')).unwrap();
+            assert!(data.contains("\\u001b[H"));
+            assert!(!data.contains('\n'));
+            events += 1;
+        }
+        assert!(events > 1);
+    }
+
+    #[test]
+    fn the_export_frame_fits_the_tallest_cabinet() {
+        for cell_w in ["2", "3"] {
+            let mut game = game(config_from(&[("STACK_CELL_W", cell_w), ("STACK_TIME_LIMIT", "60")]).unwrap());
+            for (id, command) in [(1, "make"), (2, "cargo build")] {
+                game.handle_command_event(CommandEvent::Start {
+                    id,
+                    command: command.to_string(),
+                });
+            }
+            game.handle_command_event(CommandEvent::End {
+                id: 1,
+                exit_code: 0,
+                stderr_lines: None,
+                duration_ms: None,
+            });
+            game.apply_action(crate::game::Action::Priority);
+            game.lines_cleared = 1;
+            game.freeze_left = Duration::from_secs(5);
+            let (width, height) = export_size(&game.config);
+            let lines = buffer_lines(&render_to_buffer(&game, &View::default(), width, height), false);
+            for label in ["CMD/LN:", "FROZEN:", "OK:", "TIME:", "PRIO:"] {
+                assert!(lines.iter().any(|l| l.contains(label)), "{label} missing: {lines:#?}");
+            }
+            // The whole well at full cell width, ceiling to floor, with
+            // CONTROLS and the cabinet floor below it.
+            let walls = "─".repeat(crate::BOARD_W * game.config.cell_w);
+            let ceiling = lines.iter().position(|l| l.contains(&format!("┌{walls}┐"))).expect("full-width well");
+            let col = lines[ceiling].chars().position(|ch| ch == '┌').unwrap();
+            assert_eq!(lines[ceiling + crate::PLAY_H - 1].chars().nth(col), Some('└'), "{lines:#?}");
+            assert!(lines[ceiling + crate::PLAY_H..].iter().any(|l| l.contains("Ctrl+Space")));
+            assert!(lines.last().unwrap().starts_with('┗'));
+        }
+    }
+}
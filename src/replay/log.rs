@@ -0,0 +1,234 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::Settings;
+use crate::game::{Action, Input};
+use crate::{CommandEvent, Config};
+
+// Event log format, one input per line:
+//
+//   # waitris event log v2
+//   SEED <u64>
+//   SET <NAME>=<value>                 (one per recorded setting, escaped like START)
+//   <wall ms> START <id> <command>     (command with `\\` and `\n` escaped)
+//   <wall ms> END <id> <exit code> [stderr=<lines>] [ms=<duration>]
+//   <wall ms> KEY <action>
+//   <wall ms> TICK
+//   <wall ms> FRAME <clock step in µs>
+//   <wall ms> INJECT <shape> <column> <payload>   (debug builds and flag only)
+//
+// Wall time only paces playback; the game clock advances solely via FRAME.
+// A replay is configured from the SET lines alone, so a v1 log (which has
+// none) replays with the defaults.
+pub const LOG_HEADER: &str = "# waitris event log v2";
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub at: Duration,
+    pub input: Input,
+}
+
+#[derive(Clone, Debug)]
+pub struct EventLog {
+    pub seed: u64,
+    pub settings: Settings,
+    pub entries: Vec<LogEntry>,
+}
+
+impl EventLog {
+    pub fn config(&self) -> Result<Config, String> {
+        Config::from_settings(&self.settings).map_err(|e| format!("recorded setting {e}"))
+    }
+}
+
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, seed: u64, settings: &Settings) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{LOG_HEADER}")?;
+        writeln!(out, "SEED {seed}")?;
+        for (name, value) in settings {
+            writeln!(out, "SET {name}={}", escape(value))?;
+        }
+        Ok(Self { out })
+    }
+
+    pub fn record(&mut self, at: Duration, input: &Input) {
+        let _ = writeln!(self.out, "{} {}", at.as_millis(), format_input(input));
+        // Flush once per pass so a crash loses at most one frame of input.
+        if let Input::Frame(_) = input {
+            let _ = self.out.flush();
+        }
+    }
+}
+
+pub fn format_input(input: &Input) -> String {
+    match input {
+        Input::Command(CommandEvent::Start { id, command }) => {
+            format!("START {id} {}", escape(command))
+        }
+        Input::Command(CommandEvent::End {
+            id,
+            exit_code,
+            stderr_lines,
+            duration_ms,
+        }) => {
+            let mut line = format!("END {id} {exit_code}");
+            if let Some(lines) = stderr_lines {
+                line.push_str(&format!(" stderr={lines}"));
+            }
+            if let Some(ms) = duration_ms {
+                line.push_str(&format!(" ms={ms}"));
+            }
+            line
+        }
+        Input::Action(action) => format!("KEY {}", action.name()),
+        Input::Gravity => "TICK".to_string(),
+        Input::Frame(dt) => format!("FRAME {}", dt.as_micros()),
+        Input::Inject { shape, col, payload } => {
+            format!("INJECT {} {col} {}", shape.letter(), escape(&payload.iter().collect::<String>()))
+        }
+    }
+}
+
+pub fn parse_log(text: &str) -> Result<EventLog, String> {
+    let mut seed = None;
+    let mut settings = Settings::new();
+    let mut entries = Vec::new();
+    for (idx, raw) in text.lines().enumerate() {
+        let line = idx + 1;
+        let raw = raw.trim_end_matches('\r');
+        if raw.trim().is_empty() || raw.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = raw.strip_prefix("SEED ") {
+            let value = rest.trim().parse().map_err(|_| format!("line {line}: bad seed `{rest}`"))?;
+            seed = Some(value);
+            continue;
+        }
+        if let Some(rest) = raw.strip_prefix("SET ") {
+            let (name, value) = parse_setting(rest).map_err(|e| format!("line {line}: {e}"))?;
+            settings.insert(name, value);
+            continue;
+        }
+        let (at, input) = parse_entry(raw).map_err(|e| format!("line {line}: {e}"))?;
+        entries.push(LogEntry { at, input });
+    }
+    let seed = seed.ok_or("missing SEED line")?;
+    Ok(EventLog { seed, settings, entries })
+}
+
+pub(super) fn parse_setting(rest: &str) -> Result<(String, String), String> {
+    match rest.split_once('=') {
+        Some((name, value)) if name.starts_with("STACK_") => Ok((name.to_string(), unescape(value))),
+        _ => Err(format!("expected `SET STACK_<NAME>=<value>`, got `{rest}`")),
+    }
+}
+
+pub(super) fn parse_entry(raw: &str) -> Result<(Duration, Input), String> {
+    let (at, rest) = raw.split_once(' ').ok_or("expected `<ms> <KIND> ...`")?;
+    let at = at.parse().map_err(|_| format!("bad timestamp `{at}`"))?;
+    let (kind, args) = rest.split_once(' ').unwrap_or((rest, ""));
+    let input = match kind {
+        "START" => {
+            let (id, command) = args.split_once(' ').unwrap_or((args, ""));
+            Input::Command(CommandEvent::Start {
+                id: parse_num(id, "run id")?,
+                command: unescape(command),
+            })
+        }
+        "END" => {
+            let mut parts = args.split_whitespace();
+            let id = parse_num(parts.next().unwrap_or(""), "run id")?;
+            let code = parse_num(parts.next().unwrap_or(""), "exit code")?;
+            let (mut stderr_lines, mut duration_ms) = (None, None);
+            for field in parts {
+                if let Some(lines) = field.strip_prefix("stderr=") {
+                    stderr_lines = Some(parse_num(lines, "stderr line count")?);
+                } else if let Some(ms) = field.strip_prefix("ms=") {
+                    duration_ms = Some(parse_num(ms, "duration")?);
+                } else {
+                    return Err(format!("unknown END field `{field}`"));
+                }
+            }
+            Input::Command(CommandEvent::End {
+                id,
+                exit_code: code,
+                stderr_lines,
+                duration_ms,
+            })
+        }
+        "KEY" => Input::Action(Action::from_name(args.trim()).ok_or_else(|| format!("unknown key action `{args}`"))?),
+        "TICK" => Input::Gravity,
+        "INJECT" => Input::parse_inject(&unescape(args))?,
+        "FRAME" => Input::Frame(Duration::from_micros(parse_num(args.trim(), "frame step")?)),
+        other => return Err(format!("unknown entry kind `{other}`")),
+    };
+    Ok((Duration::from_millis(at), input))
+}
+
+fn parse_num<T: std::str::FromStr>(text: &str, what: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("bad {what} `{text}`"))
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+    use crate::testing::settings;
+
+    #[test]
+    fn recorded_settings_and_inputs_read_back() {
+        let path = env::temp_dir().join(format!("stack-game-record-test-{}.log", std::process::id()));
+        let settings = settings(&[("STACK_IRONMAN", "1"), ("STACK_EXCLUDE", "^ls\\b;\n^cd")]);
+        let inputs = [
+            Input::Command(CommandEvent::Start {
+                id: 3,
+                command: "echo 'a\\b'\nls".to_string(),
+            }),
+            Input::Frame(Duration::from_micros(16_667)),
+            Input::Action(Action::HardDrop),
+        ];
+        let mut recorder = Recorder::create(&path, 42, &settings).unwrap();
+        for (i, input) in inputs.iter().enumerate() {
+            recorder.record(Duration::from_millis(i as u64 * 10), input);
+        }
+        drop(recorder);
+        let log = parse_log(&fs::read_to_string(&path).unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(log.seed, 42);
+        assert_eq!(log.settings, settings);
+        let read: Vec<String> = log.entries.iter().map(|e| format_input(&e.input)).collect();
+        let written: Vec<String> = inputs.iter().map(format_input).collect();
+        assert_eq!(read, written);
+        assert!(log.config().unwrap().ironman);
+    }
+}
@@ -0,0 +1,97 @@
+pub mod diff;
+pub mod export;
+pub mod log;
+pub mod script;
+pub mod validate;
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{stdout, BufWriter, Write};
+use std::thread;
+
+use crate::replay::export::{export_size, render_frames, write_export, ExportFormat};
+use crate::replay::log::{parse_log, EventLog, LogEntry};
+use crate::{Config, Game};
+
+pub use log::Recorder;
+
+// Steps a fresh game through a recorded log, one entry at a time.
+pub struct Player<'a> {
+    pub game: Game,
+    entries: &'a [LogEntry],
+    next: usize,
+}
+
+impl<'a> Player<'a> {
+    pub fn new(log: &'a EventLog, mut config: Config) -> Self {
+        config.record = None;
+        config.game_over_dir = None;
+        Self {
+            game: Game::seeded(config, log.seed),
+            entries: &log.entries,
+            next: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&'a LogEntry> {
+        self.entries.get(self.next)
+    }
+
+    pub fn step(&mut self) -> Option<&'a LogEntry> {
+        let entry = self.entries.get(self.next)?;
+        self.next += 1;
+        self.game.apply_input(entry.input.clone());
+        Some(entry)
+    }
+}
+
+const REPLAY_USAGE: &str = "usage: stack-game replay <log> [--export <file>] [--format cast|ansi]";
+
+// `stack-game replay`: play a recorded log back in the terminal, or export it.
+pub fn run_cli(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut log_path = None;
+    let mut export_path = None;
+    let mut format = ExportFormat::Cast;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--export" => export_path = Some(args.next().ok_or(REPLAY_USAGE)?),
+            "--format" => {
+                let name = args.next().ok_or(REPLAY_USAGE)?;
+                format = ExportFormat::from_name(name).ok_or_else(|| format!("unknown format `{name}`; {REPLAY_USAGE}"))?;
+            }
+            path if log_path.is_none() && !path.starts_with('-') => log_path = Some(path),
+            _ => return Err(REPLAY_USAGE.into()),
+        }
+    }
+    let log_path = log_path.ok_or(REPLAY_USAGE)?;
+    let text = fs::read_to_string(log_path).map_err(|e| format!("cannot read {log_path}: {e}"))?;
+    let log = parse_log(&text).map_err(|e| format!("{log_path}: {e}"))?;
+    let config = log.config()?;
+    let size = export_size(&config);
+    let frames = render_frames(&log, config);
+
+    match export_path {
+        Some(path) => {
+            let mut out = BufWriter::new(File::create(path).map_err(|e| format!("cannot create {path}: {e}"))?);
+            write_export(&frames, size, format, &mut out)?;
+            out.flush()?;
+            eprintln!("wrote {} frames to {path}", frames.len());
+        }
+        None => {
+            let mut out = stdout();
+            write!(out, "\x1b[2J")?;
+            let mut shown_at = None;
+            for frame in &frames {
+                if let Some(prev) = shown_at {
+                    thread::sleep(frame.at.saturating_sub(prev));
+                }
+                shown_at = Some(frame.at);
+                write!(out, "\x1b[H{}", frame.lines.join("\n"))?;
+                out.flush()?;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::fs;
+use std::io::{stdout, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::game::{Action, Input};
+use crate::replay::export::export_size;
+use crate::ui::{buffer_lines, render_to_buffer, View};
+use crate::{CommandEvent, Config, Game};
+
+// Hand-written demo scripts, one step per line:
+//
+//   START <id> <command>
+//   END <id> <exit code>
+//   TICK                      (one gravity step)
+//   LEFT | RIGHT | SOFT | ROTATE | DROP | RESTART
+//   WAIT <ms>                 (advance the game clock, running effect timers)
+//   INJECT <shape> <col> [text]  (swap in a piece, leftmost cell at that column)
+//
+// Blank lines and `#` comments are skipped.
+pub enum Step {
+    Input(Input),
+    Wait(Duration),
+}
+
+// Clock advanced per effect-timer pass during a WAIT, matching the app loop.
+const WAIT_FRAME: Duration = Duration::from_millis(50);
+
+pub fn parse_script(text: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (idx, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let step = parse_step(line).map_err(|e| format!("line {}: {e}", idx + 1))?;
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+fn parse_step(line: &str) -> Result<Step, String> {
+    let (word, args) = line.split_once(' ').unwrap_or((line, ""));
+    let args = args.trim();
+    let action = |action| Ok(Step::Input(Input::Action(action)));
+    match word {
+        "START" => {
+            let (id, command) = args.split_once(' ').unwrap_or((args, ""));
+            Ok(Step::Input(Input::Command(CommandEvent::Start {
+                id: parse_num(id, "run id")?,
+                command: command.trim().to_string(),
+            })))
+        }
+        "END" => {
+            let mut parts = args.split_whitespace();
+            let id = parse_num(parts.next().unwrap_or(""), "run id")?;
+            let code = parse_num(parts.next().unwrap_or(""), "exit code")?;
+            if let Some(extra) = parts.next() {
+                return Err(format!("unexpected `{extra}` after END"));
+            }
+            Ok(Step::Input(Input::Command(CommandEvent::End {
+                id,
+                exit_code: code,
+                stderr_lines: None,
+                duration_ms: None,
+            })))
+        }
+        "TICK" => Ok(Step::Input(Input::Gravity)),
+        "LEFT" => action(Action::Left),
+        "RIGHT" => action(Action::Right),
+        "SOFT" => action(Action::SoftDrop),
+        "ROTATE" => action(Action::Rotate),
+        "DROP" => action(Action::HardDrop),
+        "RESTART" => action(Action::Restart),
+        "INJECT" => Input::parse_inject(args).map(Step::Input),
+        "WAIT" => Ok(Step::Wait(Duration::from_millis(parse_num(args, "wait")?))),
+        other => Err(format!("unknown step `{other}`")),
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(text: &str, what: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("bad {what} `{text}`"))
+}
+
+// Run the steps against a fresh game. With `render`, the screen is drawn
+// after every step and WAITs pause for real.
+pub fn run_script(steps: &[Step], config: Config, render: bool) -> Result<Game, Box<dyn Error>> {
+    let (width, height) = export_size(&config);
+    let mut game = Game::with_config(config);
+    let view = View::default();
+    let mut out = stdout();
+    if render {
+        write!(out, "\x1b[2J")?;
+    }
+    for step in steps {
+        match step {
+            Step::Input(input) => game.apply_input(input.clone()),
+            Step::Wait(total) => {
+                let mut left = *total;
+                while !left.is_zero() {
+                    let dt = left.min(WAIT_FRAME);
+                    game.apply_input(Input::Frame(dt));
+                    left -= dt;
+                    if render {
+                        thread::sleep(dt);
+                    }
+                }
+            }
+        }
+        if render {
+            let lines = buffer_lines(&render_to_buffer(&game, &view, width, height), true);
+            write!(out, "\x1b[H{}", lines.join("\n"))?;
+            out.flush()?;
+        }
+    }
+    if render {
+        writeln!(out)?;
+    }
+    Ok(game)
+}
+
+// `stack-game --script <file> [--render]`: print the final score when done.
+pub fn run_file(path: &str, config: Config, render: bool) -> Result<(), Box<dyn Error>> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    let steps = parse_script(&text).map_err(|e| format!("{path}: {e}"))?;
+    let game = run_script(&steps, config, render)?;
+    println!(
+        "score {} lines {} level {}{}",
+        game.score,
+        game.lines_cleared,
+        game.level(),
+        if game.game_over { " (game over)" } else { "" }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_script_that_clears_a_line_scores_it() {
+        let script = "# fill the bottom row
+INJECT I 0
+DROP
+INJECT I 4
+DROP
+INJECT O 8
+DROP
+WAIT 200
+";
+        let steps = parse_script(script).unwrap();
+        let game = run_script(&steps, Config::default(), false).unwrap();
+        assert_eq!(game.lines_cleared, 1);
+        assert_eq!(game.score, 100);
+    }
+
+    #[test]
+    fn script_errors_name_the_line() {
+        let err = parse_script("TICK\n\nHOP 3\n").err().unwrap();
+        assert_eq!(err, "line 3: unknown step `HOP`");
+    }
+}
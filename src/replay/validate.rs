@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+
+use crate::game::Input;
+use crate::config::Settings;
+use crate::replay::log::{parse_entry, parse_setting, EventLog, LogEntry};
+use crate::replay::Player;
+use crate::CommandEvent;
+
+const VALIDATE_USAGE: &str = "usage: stack-game validate <log>";
+
+// `stack-game validate`: check a recorded log for the mistakes hand-wired
+// shell hooks tend to make, then replay what parsed through a game.
+pub fn run_cli(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [path] = args else {
+        return Err(VALIDATE_USAGE.into());
+    };
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    let (log, problems) = validate_log(&bytes);
+    for (line, problem) in &problems {
+        match line {
+            0 => println!("{path}: {problem}"),
+            line => println!("{path}:{line}: {problem}"),
+        }
+    }
+    if let Some(log) = log {
+        let mut player = Player::new(&log, log.config()?);
+        while player.step().is_some() {}
+        let game = &player.game;
+        println!(
+            "replayed {} events: score {} lines {}{}",
+            log.entries.len(),
+            game.score,
+            game.lines_cleared,
+            if game.game_over { " (game over)" } else { "" }
+        );
+    }
+    if problems.is_empty() {
+        println!("{path}: no problems found");
+        Ok(())
+    } else {
+        Err(format!("{} problem(s) in {path}", problems.len()).into())
+    }
+}
+
+// Every problem in the log by line number (0 for the log as a whole), plus
+// the entries that parsed when there is a seed to replay them with.
+pub fn validate_log(bytes: &[u8]) -> (Option<EventLog>, Vec<(usize, String)>) {
+    let mut problems = Vec::new();
+    let mut seed = None;
+    let mut settings = Settings::new();
+    let mut entries = Vec::new();
+    let mut open: HashMap<u64, usize> = HashMap::new();
+    let mut last_at = Duration::ZERO;
+    for (idx, raw) in bytes.split(|&b| b == b'\n').enumerate() {
+        let line = idx + 1;
+        let text = match std::str::from_utf8(raw) {
+            Ok(text) => text.to_string(),
+            Err(_) => {
+                problems.push((line, "not valid UTF-8".to_string()));
+                String::from_utf8_lossy(raw).into_owned()
+            }
+        };
+        let text = text.trim_end_matches('\r');
+        if text.trim().is_empty() || text.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = text.strip_prefix("SEED ") {
+            match rest.trim().parse() {
+                Ok(value) => seed = Some(value),
+                Err(_) => problems.push((line, format!("bad seed `{rest}`"))),
+            }
+            continue;
+        }
+        if let Some(rest) = text.strip_prefix("SET ") {
+            match parse_setting(rest) {
+                Ok((name, value)) => {
+                    settings.insert(name, value);
+                }
+                Err(e) => problems.push((line, e)),
+            }
+            continue;
+        }
+        let (at, input) = match parse_entry(text) {
+            Ok(entry) => entry,
+            Err(e) => {
+                problems.push((line, e));
+                continue;
+            }
+        };
+        if at < last_at {
+            problems.push((line, format!("timestamp {} ms is earlier than the line before", at.as_millis())));
+        }
+        last_at = last_at.max(at);
+        match &input {
+            Input::Command(CommandEvent::Start { id, command }) => {
+                if command.trim().is_empty() {
+                    problems.push((line, format!("START {id} has an empty command")));
+                }
+                if let Some(first) = open.insert(*id, line) {
+                    problems.push((line, format!("duplicate START {id} (still open from line {first})")));
+                }
+            }
+            Input::Command(CommandEvent::End { id, .. }) if open.remove(id).is_none() => {
+                problems.push((line, format!("orphan END {id} with no open START")));
+            }
+            _ => {}
+        }
+        entries.push(LogEntry { at, input });
+    }
+    for (id, line) in open {
+        problems.push((line, format!("run {id} never ended")));
+    }
+    if seed.is_none() {
+        problems.push((0, "missing SEED line".to_string()));
+    }
+    problems.sort_by_key(|&(line, _)| line);
+    (seed.map(|seed| EventLog { seed, settings, entries }), problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orphan_end_is_flagged_with_its_line() {
+        let log = "# waitris event log v2\nSEED 1\n0 START 1 cargo build\n10 END 1 0\n20 END 7 1\n";
+        let (log, problems) = validate_log(log.as_bytes());
+        assert_eq!(problems, vec![(5, "orphan END 7 with no open START".to_string())]);
+        assert_eq!(log.unwrap().entries.len(), 3);
+    }
+
+    #[test]
+    fn hook_mistakes_are_each_reported() {
+        let mut bytes = b"0 START 1 make\n5 START 1 make\n3 START 2  \n9 KEY jump\n".to_vec();
+        bytes.extend_from_slice(b"10 START 3 \xff\n");
+        let (log, problems) = validate_log(&bytes);
+        assert!(log.is_none());
+        let flagged = |line: usize, text: &str| problems.iter().any(|(l, p)| *l == line && p.contains(text));
+        assert!(flagged(0, "missing SEED"));
+        assert!(flagged(2, "duplicate START 1"));
+        assert!(flagged(3, "earlier than the line before"));
+        assert!(flagged(3, "empty command"));
+        assert!(flagged(4, "unknown key action"));
+        assert!(flagged(5, "not valid UTF-8"));
+        assert!(flagged(5, "never ended"));
+    }
+}
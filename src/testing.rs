@@ -0,0 +1,28 @@
+// Fixtures shared by the unit tests.
+
+use crate::config::Settings;
+use crate::{Config, Game};
+
+pub fn settings(pairs: &[(&str, &str)]) -> Settings {
+    pairs.iter().map(|&(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+// Parsed the way `STACK_*` variables or a log header would be.
+pub fn config_from(pairs: &[(&str, &str)]) -> Result<Config, String> {
+    Config::from_settings(&settings(pairs))
+}
+
+pub fn config_with(tweak: impl FnOnce(&mut Config)) -> Config {
+    let mut config = Config::default();
+    tweak(&mut config);
+    config
+}
+
+// Seeded, so shapes and garbage holes are the same on every run.
+pub fn game(config: Config) -> Game {
+    Game::seeded(config, 7)
+}
+
+pub fn game_with(tweak: impl FnOnce(&mut Config)) -> Game {
+    game(config_with(tweak))
+}
@@ -0,0 +1,49 @@
+use crate::config::ScoreFormat;
+
+const SHORT_UNITS: [(u64, char); 3] = [(1_000_000_000, 'B'), (1_000_000, 'M'), (1_000, 'k')];
+
+// `12345` as `12345`, `12,345` or `12.3k`. Abbreviations truncate rather
+// than round, so a score never reads higher than it is.
+pub fn format_score(score: u64, format: ScoreFormat) -> String {
+    match format {
+        ScoreFormat::Plain => score.to_string(),
+        ScoreFormat::Grouped => {
+            let digits = score.to_string();
+            let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+            for (i, ch) in digits.chars().enumerate() {
+                if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                    out.push(',');
+                }
+                out.push(ch);
+            }
+            out
+        }
+        ScoreFormat::Short => {
+            let Some(&(unit, suffix)) = SHORT_UNITS.iter().find(|&&(unit, _)| score >= unit) else {
+                return score.to_string();
+            };
+            let whole = score / unit;
+            if whole >= 100 {
+                format!("{whole}{suffix}")
+            } else {
+                format!("{whole}.{}{suffix}", score % unit * 10 / unit)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_format_plain_grouped_and_short() {
+        assert_eq!(format_score(12345, ScoreFormat::Plain), "12345");
+        assert_eq!(format_score(999, ScoreFormat::Grouped), "999");
+        assert_eq!(format_score(1234567, ScoreFormat::Grouped), "1,234,567");
+        assert_eq!(format_score(999, ScoreFormat::Short), "999");
+        assert_eq!(format_score(12_399, ScoreFormat::Short), "12.3k");
+        assert_eq!(format_score(4_560_000, ScoreFormat::Short), "4.5M");
+        assert_eq!(format_score(250_000, ScoreFormat::Short), "250k");
+    }
+}
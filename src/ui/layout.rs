@@ -0,0 +1,64 @@
+use crate::{BOARD_W, PLAY_H};
+
+// Each cell draws as a letter plus filler, so it can't get narrower than two.
+pub const MIN_CELL_W: usize = 2;
+
+// Columns of the well itself: `cell_w` per board column between two walls.
+pub fn play_width(cell_w: usize) -> usize {
+    BOARD_W * cell_w + 2
+}
+
+// Text rows the INFO box can grow to (its right column with every optional
+// stat showing) and the CONTROLS box always has.
+pub const INFO_MAX_ROWS: usize = 7;
+pub const CONTROLS_ROWS: usize = 5;
+
+// Terminal rows the full cabinet needs: both boxes at their tallest, each in
+// its border, around the well and inside the cabinet border.
+pub const fn required_height() -> u16 {
+    (INFO_MAX_ROWS + 2 + PLAY_H + CONTROLS_ROWS + 2 + 2) as u16
+}
+
+// Terminal columns the cabinet needs: the well plus the cabinet border around it.
+pub fn required_width(cell_w: usize) -> u16 {
+    (play_width(cell_w) + 2) as u16
+}
+
+// The widest cell width, up to the requested one, whose cabinet fits the
+// terminal; an error explaining what to change when even the narrowest doesn't.
+pub fn fit_layout(term_w: u16, cell_w: usize) -> Result<usize, String> {
+    (MIN_CELL_W..=cell_w.max(MIN_CELL_W))
+        .rev()
+        .find(|&w| required_width(w) <= term_w)
+        .ok_or_else(|| {
+            format!(
+                "terminal is {term_w} columns wide but waitris needs at least {}; \
+                 increase the terminal width or reduce the cell width",
+                required_width(MIN_CELL_W),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_layout_narrows_cells_to_the_terminal() {
+        assert_eq!(required_width(2), 24);
+        assert_eq!(fit_layout(80, 2), Ok(2));
+        assert_eq!(fit_layout(80, 3), Ok(3));
+        assert_eq!(fit_layout(40, 4), Ok(3));
+        assert_eq!(fit_layout(33, 3), Ok(2));
+        assert_eq!(fit_layout(24, 2), Ok(2));
+        assert!(fit_layout(23, 2).unwrap_err().contains("at least 24"));
+    }
+
+    #[test]
+    fn the_well_is_board_w_cells_of_the_configured_width() {
+        for cell_w in [2, 3, 4] {
+            assert_eq!(play_width(cell_w) - 2, BOARD_W * cell_w);
+            assert_eq!(required_width(cell_w) as usize, BOARD_W * cell_w + 4);
+        }
+    }
+}
@@ -1,3 +1,9 @@
+mod format;
+mod layout;
+mod palette;
 mod render;
+mod text;
 
-pub use render::draw_game;
+pub use layout::{required_height, required_width};
+pub use render::{draw_game, View};
+pub use text::{buffer_lines, render_to_buffer};
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use ratatui::style::Color;
+
+use crate::Config;
+
+// Foregrounds cycled by run id, so each command's pieces keep one color from
+// the NEXT preview through the fall into the stack. Red is left out so bombs
+// stand apart.
+const RUN_PALETTE: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::LightBlue,
+    Color::White,
+];
+
+pub const BOMB_COLOR: Color = Color::LightRed;
+
+// Infected filler, stepped along once per `SHIMMER_STEP` and offset by cell so
+// the shimmer creeps across the stack instead of blinking in unison.
+const INFECTION_SHIMMER: [char; 4] = ['░', '▒', '▓', '▒'];
+const SHIMMER_STEP: Duration = Duration::from_millis(150);
+// Without color no shimmer shade is safe to tell apart from other cells.
+const INFECTION_MONO_FILL: char = '×';
+
+// Keyed to the game clock, like the STATUS blink, so replays render alike.
+pub fn infection_fill(config: &Config, elapsed: Duration, x: usize, y: usize) -> char {
+    if config.infection_color.is_none() {
+        return INFECTION_MONO_FILL;
+    }
+    if !config.infection_shimmer || config.reduced_motion {
+        return INFECTION_SHIMMER[0];
+    }
+    let phase = (elapsed.as_millis() / SHIMMER_STEP.as_millis()) as usize + x + y;
+    INFECTION_SHIMMER[phase % INFECTION_SHIMMER.len()]
+}
+
+pub fn color_for_run(id: u64) -> Color {
+    RUN_PALETTE[(id % RUN_PALETTE.len() as u64) as usize]
+}
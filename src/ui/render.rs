@@ -1,22 +1,44 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
 use ratatui::prelude::*;
 use ratatui::text::Line;
 use ratatui::widgets::{Block, BorderType, Borders, Paragraph};
 
-use crate::{Game, BOARD_H, BOARD_W, CELL_W, MIN_PANE_WIDTH, PLAY_H, PLAY_W};
+use crate::ui::layout::{fit_layout, play_width, required_width, CONTROLS_ROWS, INFO_MAX_ROWS, MIN_CELL_W};
+use crate::ui::palette::{color_for_run, infection_fill, BOMB_COLOR};
+use crate::{Game, BOARD_H, BOARD_W, PANEL_EXPAND, PLAY_H};
+use crate::config::{GravityDir, NarrowLayout};
+use crate::ui::format::format_score;
 use crate::game::Cell;
 
-pub fn draw_game(frame: &mut Frame, game: &Game) {
+// Display toggles owned by the app loop rather than the game.
+#[derive(Default)]
+pub struct View {
+    pub heatmap: bool,
+    pub origins: bool,
+}
+
+// Upcoming pieces listed on the NEXT line (more with a longer preview lead);
+// `*` marks a bomb.
+const PREVIEW_LEN: usize = 3;
+
+const FAILURE_DIGEST_LEN: usize = 3;
+
+const GAUGE_W: usize = 4;
+
+const HEAT_RAMP: [Color; 5] = [Color::Blue, Color::Cyan, Color::Green, Color::Yellow, Color::Red];
+
+pub fn draw_game(frame: &mut Frame, game: &Game, view: &View) {
     let area = frame.size();
 
-    if area.width < MIN_PANE_WIDTH {
-        let msg = Paragraph::new(format!("RESIZE PANE (min width: {})", MIN_PANE_WIDTH))
+    // Narrow the cells, down to the minimum, before giving up on the pane.
+    let Ok(cell_w) = fit_layout(area.width, game.config.cell_w) else {
+        let msg = Paragraph::new(format!("RESIZE PANE (min width: {})", required_width(MIN_CELL_W)))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL).title("STACK"));
         frame.render_widget(msg, area);
         return;
-    }
+    };
 
     // Outer "cabinet" frame.
     let cabinet = Block::default()
@@ -27,7 +49,7 @@ pub fn draw_game(frame: &mut Frame, game: &Game) {
     let cabinet_inner = cabinet.inner(area);
     frame.render_widget(cabinet, area);
 
-    let well_w = PLAY_W as u16;
+    let well_w = play_width(cell_w) as u16;
     let well_h = PLAY_H as u16;
 
     let col_rect = Layout::default()
@@ -39,8 +61,17 @@ pub fn draw_game(frame: &mut Frame, game: &Game) {
         ])
         .split(cabinet_inner)[1];
 
-    let info_h = 5u16;
-    let controls_h = 5u16;
+    if area.width < game.config.min_pane_width {
+        draw_narrow(frame, game, view, cell_w, col_rect);
+        return;
+    }
+
+    let (info_left, info_right) = info_lines(game);
+    let (controls_left, controls_right) = controls_lines();
+    debug_assert!(info_left.len().max(info_right.len()) <= INFO_MAX_ROWS);
+    debug_assert!(controls_left.len().max(controls_right.len()) == CONTROLS_ROWS);
+    let info_h = info_left.len().max(info_right.len()) as u16 + 2;
+    let controls_h = controls_left.len().max(controls_right.len()) as u16 + 2;
     let stack = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -56,10 +87,9 @@ pub fn draw_game(frame: &mut Frame, game: &Game) {
     let well_rect = stack[2];
     let mut controls_rect = stack[3];
     // Widen info/controls boxes slightly while keeping them centered in the cabinet.
-    let expand = 8u16;
     let max_right = cabinet_inner.x + cabinet_inner.width;
-    let new_x = info_rect.x.saturating_sub(expand);
-    let mut new_w = info_rect.width.saturating_add(expand * 2);
+    let new_x = info_rect.x.saturating_sub(PANEL_EXPAND);
+    let mut new_w = info_rect.width.saturating_add(PANEL_EXPAND * 2);
     if new_x + new_w > max_right {
         new_w = max_right.saturating_sub(new_x);
     }
@@ -68,143 +98,384 @@ pub fn draw_game(frame: &mut Frame, game: &Game) {
     controls_rect.x = new_x;
     controls_rect.width = new_w;
 
-    draw_info(frame, game, info_rect);
-    draw_playfield(frame, game, well_rect);
-    draw_controls(frame, controls_rect);
+    draw_columns(frame, "INFO", info_left, info_right, info_rect);
+    draw_playfield(frame, game, view, cell_w, well_rect);
+    draw_columns(frame, "CONTROLS", controls_left, controls_right, controls_rect);
+}
+
+// Too narrow for the INFO and CONTROLS boxes: the well alone, the well with
+// the stats in one column underneath, or the well under a one-line summary.
+fn draw_narrow(frame: &mut Frame, game: &Game, view: &View, cell_w: usize, col_rect: Rect) {
+    let layout = game.config.narrow_layout;
+    let well_h = PLAY_H as u16;
+    let below: Vec<Line> = match layout {
+        NarrowLayout::Hide | NarrowLayout::Inline => Vec::new(),
+        NarrowLayout::Stack => {
+            let (left, right) = info_lines(game);
+            left.into_iter().chain(right).collect()
+        }
+    };
+    let above_h = u16::from(layout == NarrowLayout::Inline);
+    let below_h = if below.is_empty() { 0 } else { below.len() as u16 + 2 };
+    let stack = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(above_h),
+            Constraint::Length(well_h),
+            Constraint::Length(below_h),
+            Constraint::Min(0),
+        ])
+        .split(col_rect);
+
+    if layout == NarrowLayout::Inline {
+        let score = format_score(shown_score(game), game.config.score_format);
+        let line = format!("S:{score} L:{} B:{}", game.lines_cleared, game.bombs);
+        frame.render_widget(Paragraph::new(line).alignment(Alignment::Center), stack[1]);
+    }
+    draw_playfield(frame, game, view, cell_w, stack[2]);
+    if !below.is_empty() {
+        let block = Block::default().title("INFO").borders(Borders::ALL);
+        frame.render_widget(Paragraph::new(below).block(block), stack[3]);
+    }
 }
 
-fn draw_playfield(frame: &mut Frame, game: &Game, play_rect: Rect) {
-    let mut grid = vec![vec![' '; PLAY_W]; PLAY_H];
+fn draw_playfield(frame: &mut Frame, game: &Game, view: &View, cell_w: usize, play_rect: Rect) {
+    let play_w = play_width(cell_w);
+    let mut grid = vec![vec![' '; play_w]; PLAY_H];
+    let mut styles = vec![vec![Style::default(); play_w]; PLAY_H];
 
-    // Border: top/ceiling, sides, heavy floor.
+    // Border: top/ceiling, sides, heavy floor. The heavy edge is wherever the
+    // stack rests, so it moves to the ceiling with inverted gravity.
+    let (ceiling, floor) = match game.config.gravity {
+        GravityDir::Down => ('─', '═'),
+        GravityDir::Up => ('═', '─'),
+    };
     grid[0][0] = '┌';
-    grid[0][PLAY_W - 1] = '┐';
-    for x in 1..PLAY_W - 1 {
-        grid[0][x] = '─';
+    grid[0][play_w - 1] = '┐';
+    for cell in &mut grid[0][1..play_w - 1] {
+        *cell = ceiling;
     }
-    for y in 1..PLAY_H - 1 {
-        grid[y][0] = '│';
-        grid[y][PLAY_W - 1] = '│';
+    for row in &mut grid[1..PLAY_H - 1] {
+        row[0] = '│';
+        row[play_w - 1] = '│';
     }
     grid[PLAY_H - 1][0] = '└';
-    grid[PLAY_H - 1][PLAY_W - 1] = '┘';
-    for x in 1..PLAY_W - 1 {
-        grid[PLAY_H - 1][x] = '═';
+    grid[PLAY_H - 1][play_w - 1] = '┘';
+    for cell in &mut grid[PLAY_H - 1][1..play_w - 1] {
+        *cell = floor;
+    }
+    let calm = game.config.reduced_motion;
+    if let Some(tint) = height_warning(game) {
+        tint_border(&mut styles, tint);
     }
 
-    // Helper to plot a filled block in the inner area. Draw as `letter + light filler`.
-    let plot_block = |grid: &mut [Vec<char>], bx: usize, by: usize, left: char, right: char| {
-        let gx = 1 + bx * CELL_W;
+    // Helper to plot a filled block in the inner area. Draw as `letter + light filler`,
+    // tinted when the block belongs to a run or a bomb.
+    let plot_block = |grid: &mut [Vec<char>],
+                      styles: &mut [Vec<Style>],
+                      bx: usize,
+                      by: usize,
+                      (left, right): (char, char),
+                      color: Option<Color>| {
+        let gx = 1 + bx * cell_w;
         let gy = 1 + by;
-        if gy < PLAY_H && gx + 1 < PLAY_W {
+        if gy < PLAY_H && gx + cell_w < play_w {
             grid[gy][gx] = left;
-            grid[gy][gx + 1] = right;
+            for cell in &mut grid[gy][gx + 1..gx + cell_w] {
+                *cell = right;
+            }
+            if let Some(color) = color {
+                for style in &mut styles[gy][gx..gx + cell_w] {
+                    *style = style.fg(color);
+                }
+            }
         }
     };
 
-    // Locked cells (with optional lock flash override).
+    // Locked cells (with optional lock flash override), drawn short of their
+    // rows while new garbage rises in from the side the stack rests on.
+    // The falling piece and its ghost ride along so they keep their place
+    // against the stack.
+    let rise = if calm { 0 } else { game.garbage_rise_offset() };
+    let height = game.board.height;
+    let shown_row = |y: usize| match game.config.gravity {
+        GravityDir::Down => Some(y + rise).filter(|&y| y < height),
+        GravityDir::Up => y.checked_sub(rise).filter(|&y| y < height),
+    };
     for y in 0..game.board.height {
+        let Some(shown_y) = shown_row(y) else {
+            continue;
+        };
         for x in 0..game.board.width {
-            if let Cell::Filled(left_ch, right_ch) = game.board.get(x, y) {
-                let flashing = game.lock_flash_frames > 0
+            let glyphs = match game.board.get(x, y) {
+                Cell::Filled(left, right) => Some((left, right)),
+                Cell::Infected => Some((game.config.infection_glyph, infection_fill(&game.config, game.elapsed, x, y))),
+                Cell::Garbage => Some(('#', '░')),
+                Cell::Empty => None,
+            };
+            if let Some((left_ch, right_ch)) = glyphs {
+                let flashing = !calm
+                    && game.lock_flash_frames > 0
                     && game.lock_flash_cells.contains(&(x, y));
+                let origin = game.board.origin(x, y);
+                let label = origin.filter(|_| view.origins).and_then(|run| game.origin_label(run));
+                let (left_ch, right_ch) = match label {
+                    Some(label) => (label, '░'),
+                    None => (left_ch, right_ch),
+                };
                 let left = if flashing { '▓' } else { left_ch };
                 let right = if flashing { '▓' } else { right_ch };
-                plot_block(&mut grid, x, y, left, right);
+                let color = match game.board.get(x, y) {
+                    Cell::Infected => game.config.infection_color,
+                    _ => origin.map(color_for_run),
+                };
+                plot_block(&mut grid, &mut styles, x, shown_y, (left, right), color);
             }
         }
     }
 
-    if game.active_piece {
+    if let Some(current) = &game.current {
         if game.current_is_bomb {
-            // Bomb drop banner along the top inside the well.
+            // Bomb drop banner along the spawn edge of the well.
             let banner = " BOMB INBOUND ";
-            let start = ((PLAY_W as i32 - banner.len() as i32) / 2).max(1) as usize;
-            let gy = 0;
+            let start = ((play_w as i32 - banner.len() as i32) / 2).max(1) as usize;
+            let gy = match game.config.gravity {
+                GravityDir::Down => 0,
+                GravityDir::Up => PLAY_H - 1,
+            };
             for (i, ch) in banner.chars().enumerate() {
-                if start + i < PLAY_W - 1 {
+                if start + i < play_w - 1 {
                     grid[gy][start + i] = ch;
                 }
             }
         }
 
-        // Ghost piece: draw with faint glyphs.
-        let ghost = game.ghost_piece();
-        for (x, y, _) in ghost.cells() {
+        // Ghost piece: draw with faint glyphs (an assist ironman runs go without).
+        let ghost_cells = match game.ghost_piece() {
+            Some(ghost) if !game.config.ironman => ghost.cells(),
+            _ => Vec::new(),
+        };
+        for (x, y, _) in ghost_cells {
             if x >= 0 && y >= 0 {
                 let (xu, yu) = (x as usize, y as usize);
-                if xu < game.board.width && yu < game.board.height {
-                    let gx = 1 + xu * CELL_W;
+                if xu < game.board.width
+                    && let Some(yu) = shown_row(yu)
+                {
+                    let gx = 1 + xu * cell_w;
                     let gy = 1 + yu;
-                    if gy < PLAY_H && gx + 1 < PLAY_W {
-                        grid[gy][gx] = '·';
-                        grid[gy][gx + 1] = '·';
+                    if gy < PLAY_H && gx + cell_w < play_w {
+                        grid[gy][gx..gx + cell_w].fill('·');
                     }
                 }
             }
         }
 
-        // Active piece.
-        for (x, y, (left, right)) in game.current.cells_with_pairs() {
+        // Active piece, in the same color its locked cells will keep.
+        let color = if game.current_is_bomb {
+            Some(BOMB_COLOR)
+        } else {
+            game.active_run().map(color_for_run)
+        };
+        for (x, y, pair) in current.cells_with_pairs() {
             if x >= 0 && y >= 0 {
                 let (xu, yu) = (x as usize, y as usize);
-                if xu < game.board.width && yu < game.board.height {
-                    plot_block(&mut grid, xu, yu, left, right);
+                if xu < game.board.width
+                    && let Some(yu) = shown_row(yu)
+                {
+                    plot_block(&mut grid, &mut styles, xu, yu, pair, color);
                 }
             }
         }
     }
 
-    // Line clear flash overlay overrides everything in the row.
+    // Falling-behind warning along the edge the stack rests on.
+    if game.falling_behind {
+        let banner = " FALLING BEHIND ";
+        let start = ((play_w as i32 - banner.len() as i32) / 2).max(1) as usize;
+        let gy = match game.config.gravity {
+            GravityDir::Down => PLAY_H - 1,
+            GravityDir::Up => 0,
+        };
+        for (i, ch) in banner.chars().enumerate() {
+            if start + i < play_w - 1 {
+                grid[gy][start + i] = ch;
+                styles[gy][start + i] = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+            }
+        }
+    }
+
+    // Line clear flash overlay overrides everything in the row. Reduced
+    // motion points at the rows from the walls instead.
     if game.clear_flash_frames > 0 && !game.pending_clear.is_empty() {
         for &row in &game.pending_clear {
             if row < BOARD_H {
                 let gy = 1 + row;
+                if calm {
+                    grid[gy][0] = '▶';
+                    grid[gy][play_w - 1] = '◀';
+                    continue;
+                }
                 for x in 0..BOARD_W {
-                    let gx = 1 + x * CELL_W;
-                    if gy < PLAY_H && gx + 1 < PLAY_W {
-                        grid[gy][gx] = '█';
-                        grid[gy][gx + 1] = '█';
+                    let gx = 1 + x * cell_w;
+                    if gy < PLAY_H && gx + cell_w < play_w {
+                        grid[gy][gx..gx + cell_w].fill('█');
                     }
                 }
             }
         }
     }
 
-    let lines: Vec<Line> = grid
-        .iter()
-        .map(|row| Line::raw(row.iter().collect::<String>()))
-        .collect();
+    // Failed-command flash tints the whole well, or just its border.
+    if game.fail_flash_frames > 0 && calm {
+        tint_border(&mut styles, Color::Red);
+    } else if game.fail_flash_frames > 0 {
+        for row in &mut styles {
+            for style in row.iter_mut() {
+                *style = style.bg(Color::Red);
+            }
+        }
+    }
+
+    if view.heatmap {
+        let max = game.column_locks.iter().copied().max().unwrap_or(0);
+        for (x, &count) in game.column_locks.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let step = (count as usize * HEAT_RAMP.len()).div_ceil(max as usize) - 1;
+            let bg = HEAT_RAMP[step.min(HEAT_RAMP.len() - 1)];
+            let gx = 1 + x * cell_w;
+            for row in &mut styles[1..PLAY_H - 1] {
+                for style in &mut row[gx..gx + cell_w] {
+                    *style = style.bg(bg);
+                }
+            }
+        }
+    }
+
+    let lines = styled_lines(&grid, &styles);
 
     let paragraph = Paragraph::new(lines).alignment(Alignment::Left);
     frame.render_widget(paragraph, play_rect);
 
-    if game.game_over {
-        let overlay_w = (PLAY_W as u16).saturating_sub(4).max(8);
-        let overlay_h = 5u16;
+    if game.frozen() {
+        let summary = summary_lines(game);
+        let overlay_w = (play_w as u16).saturating_sub(4).max(8);
+        let overlay_h = (summary.len() as u16 + 2).min(play_rect.height);
         let popup = Rect {
             x: play_rect.x + (play_rect.width.saturating_sub(overlay_w)) / 2,
             y: play_rect.y + (play_rect.height.saturating_sub(overlay_h)) / 2,
             width: overlay_w,
             height: overlay_h,
         };
-        let overlay = Paragraph::new("GAME OVER\nPress q")
+        let overlay = Paragraph::new(summary.join("\n"))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
         frame.render_widget(overlay, popup);
     }
 }
 
-fn draw_info(frame: &mut Frame, game: &Game, area: Rect) {
+fn summary_lines(game: &Game) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(time) = game.finish_time {
+        lines.push("CLEARED!".to_string());
+        lines.push(format!("{} LINES", game.lines_cleared));
+        lines.push(format!("IN {}", format_clock(time)));
+    } else if game.time_up {
+        lines.push("TIME UP".to_string());
+        lines.push(format!("SCORE {}", format_score(game.score, game.config.score_format)));
+    } else {
+        lines.push("GAME OVER".to_string());
+    }
+    if game.config.ironman {
+        lines.push("IRONMAN".to_string());
+    }
+    if let Some(cpl) = game.commands_per_line() {
+        lines.push(format!("{cpl:.1} CMD/LINE"));
+    }
+    let digest = game.failure_digest(FAILURE_DIGEST_LEN);
+    if !digest.is_empty() {
+        lines.push("FLAKIEST".to_string());
+        for (name, count) in digest {
+            let name: String = name.chars().take(10).collect();
+            lines.push(format!("{name} x{count}"));
+        }
+    }
+    lines.push("r restart".to_string());
+    lines.push("q quit".to_string());
+    lines
+}
+
+fn format_clock(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn styled_lines(grid: &[Vec<char>], styles: &[Vec<Style>]) -> Vec<Line<'static>> {
+    grid.iter()
+        .zip(styles)
+        .map(|(chars, row_styles)| {
+            let mut spans: Vec<Span> = Vec::new();
+            let mut buf = String::new();
+            let mut current = row_styles.first().copied().unwrap_or_default();
+            for (&ch, &style) in chars.iter().zip(row_styles) {
+                if style != current && !buf.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut buf), current));
+                }
+                current = style;
+                buf.push(ch);
+            }
+            if !buf.is_empty() {
+                spans.push(Span::styled(buf, current));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn tint_border(styles: &mut [Vec<Style>], tint: Color) {
+    let height = styles.len();
+    for (y, row) in styles.iter_mut().enumerate() {
+        let width = row.len();
+        for (x, style) in row.iter_mut().enumerate() {
+            if y == 0 || y == height - 1 || x == 0 || x == width - 1 {
+                *style = style.fg(tint);
+            }
+        }
+    }
+}
+
+fn shown_score(game: &Game) -> u64 {
+    if game.config.reduced_motion {
+        game.score
+    } else {
+        game.shown_score
+    }
+}
+
+fn height_warning(game: &Game) -> Option<Color> {
+    let height = game.stack_height();
+    let reached = |limit: Option<usize>| limit.is_some_and(|rows| height >= rows);
+    if reached(game.config.danger_height) {
+        Some(Color::Red)
+    } else if reached(game.config.caution_height) {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+fn info_lines(game: &Game) -> (Vec<Line<'static>>, Vec<Line<'static>>) {
     let running = game.is_running();
     let status = if game.game_over {
         "OVER"
+    } else if game.current.is_some() && game.idle_paused() {
+        "PAUSED"
     } else if running {
-        let millis = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        if (millis / 300) % 2 == 0 {
+        // Blink off the game clock so replays render identically.
+        let millis = game.elapsed.as_millis();
+        if game.config.reduced_motion || (millis / 300).is_multiple_of(2) {
             "ACTIVE"
         } else {
             "      "
@@ -213,33 +484,95 @@ fn draw_info(frame: &mut Frame, game: &Game, area: Rect) {
         "IDLE"
     };
 
-    let block = Block::default().title("INFO").borders(Borders::ALL);
-    let inner = block.inner(area);
-    frame.render_widget(block, area);
-
-    let cols = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
-        .split(inner);
-
-    let left = Paragraph::new(vec![
-        Line::raw(format!("{:<7} {}", "SCORE:", game.score)),
+    let mut left = vec![
+        Line::raw(format!("{:<7} {}", "SCORE:", format_score(shown_score(game), game.config.score_format))),
         Line::raw(format!("{:<7} {}", "LINES:", game.lines_cleared)),
         Line::raw(format!("{:<7} {}", "STATUS:", status)),
-    ])
-    .alignment(Alignment::Left);
-    frame.render_widget(left, cols[0]);
+    ];
+    let upcoming = game.peek_next(PREVIEW_LEN.max(game.config.preview_lead));
+    if !upcoming.is_empty() {
+        let mut spans = vec![Span::raw(format!("{:<7}", "NEXT:"))];
+        for p in &upcoming {
+            let (text, color) = if p.is_bomb {
+                ("*".to_string(), Some(BOMB_COLOR))
+            } else {
+                (p.shape.letter().to_string(), p.run_id.map(color_for_run))
+            };
+            spans.push(Span::raw(" "));
+            spans.push(match color {
+                Some(color) => Span::styled(text, Style::default().fg(color)),
+                None => Span::raw(text),
+            });
+        }
+        left.push(Line::from(spans));
+    }
 
-    let right = Paragraph::new(vec![
+    let mut right = vec![
         Line::raw(format!("{:<6} {}", "BOMBS:", game.bombs)),
         Line::raw(format!("{:<6} {}", "VARIETY:", game.variety_meter)),
+    ];
+    if let Some(cpl) = game.commands_per_line() {
+        right.push(Line::raw(format!("{:<6} {cpl:.1}", "CMD/LN:")));
+    }
+    if !game.freeze_left.is_zero() {
+        let shown = Duration::from_secs(game.freeze_left.as_millis().div_ceil(1000) as u64);
+        right.push(Line::styled(
+            format!("{:<6} {}", "FROZEN:", format_clock(shown)),
+            Style::default().fg(Color::LightBlue),
+        ));
+    }
+    if let Some(pct) = game.success_rate() {
+        right.push(success_gauge(pct));
+    }
+    if let Some(left) = game.time_remaining() {
+        // Round up so the clock reads 0:00 only once time is actually up.
+        let shown = Duration::from_secs(left.as_millis().div_ceil(1000) as u64);
+        right.push(Line::raw(format!("{:<6} {}", "TIME:", format_clock(shown))));
+    }
+    if let Some(identity) = game.priority_identity() {
+        right.push(Line::styled(
+            format!("{:<6} {}", "PRIO:", identity),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    (left, right)
+}
+
+fn success_gauge(pct: u32) -> Line<'static> {
+    let filled = (pct as usize * GAUGE_W).div_ceil(100);
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(GAUGE_W - filled));
+    let color = match pct {
+        80.. => Color::Green,
+        50.. => Color::Yellow,
+        _ => Color::Red,
+    };
+    Line::from(vec![
+        Span::raw(format!("{:<6} ", "OK:")),
+        Span::styled(bar, Style::default().fg(color)),
+        Span::raw(format!(" {pct}%")),
     ])
-    .alignment(Alignment::Left);
-    frame.render_widget(right, cols[1]);
 }
 
-fn draw_controls(frame: &mut Frame, area: Rect) {
-    let block = Block::default().title("CONTROLS").borders(Borders::ALL);
+fn controls_lines() -> (Vec<Line<'static>>, Vec<Line<'static>>) {
+    let left = vec![
+        Line::raw("←/→ move"),
+        Line::raw("↓ soft"),
+        Line::raw("h heat map"),
+        Line::raw("o origins"),
+        Line::raw("q quit"),
+    ];
+    let right = vec![
+        Line::raw("↑ rotate"),
+        Line::raw("space slam"),
+        Line::raw("r restart"),
+        Line::raw("tab priority"),
+        Line::raw("Ctrl+Space swap"),
+    ];
+    (left, right)
+}
+
+fn draw_columns(frame: &mut Frame, title: &str, left: Vec<Line>, right: Vec<Line>, area: Rect) {
+    let block = Block::default().title(title).borders(Borders::ALL);
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
@@ -248,19 +581,194 @@ fn draw_controls(frame: &mut Frame, area: Rect) {
         .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
         .split(inner);
 
-    let left = Paragraph::new(vec![
-        Line::raw("←/→ move"),
-        Line::raw("↓ soft"),
-        Line::raw("q quit"),
-    ])
-    .alignment(Alignment::Left);
-    frame.render_widget(left, cols[0]);
+    frame.render_widget(Paragraph::new(left).alignment(Alignment::Left), cols[0]);
+    frame.render_widget(Paragraph::new(right).alignment(Alignment::Left), cols[1]);
+}
 
-    let right = Paragraph::new(vec![
-        Line::raw("↑ rotate"),
-        Line::raw("space slam"),
-        Line::raw("Ctrl+Space swap"),
-    ])
-    .alignment(Alignment::Left);
-    frame.render_widget(right, cols[1]);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Action, Board, Shape};
+    use crate::testing::*;
+    use crate::ui::{buffer_lines, render_to_buffer};
+
+    const TEST_W: u16 = 40;
+    const TEST_H: u16 = 60;
+
+    fn render(game: &Game, view: &View) -> Buffer {
+        render_to_buffer(game, view, TEST_W, TEST_H)
+    }
+
+    // Buffer position of the well's top-left inner cell.
+    fn well_origin(lines: &[String], cell_w: usize) -> (u16, u16) {
+        let top = format!("┌{}┐", "─".repeat(play_width(cell_w) - 2));
+        let (row, line) = lines.iter().enumerate().find(|(_, l)| l.contains(&top)).expect("well drawn");
+        let col = line[..line.find(&top).unwrap()].chars().count() + 1;
+        (col as u16, row as u16 + 1)
+    }
+
+    // The inside of the well as drawn: one string per board row.
+    fn well_rows(game: &Game, view: &View) -> Vec<String> {
+        let lines = buffer_lines(&render(game, view), false);
+        let cell_w = game.config.cell_w;
+        let (col, row) = well_origin(&lines, cell_w);
+        lines[row as usize..row as usize + BOARD_H]
+            .iter()
+            .map(|l| l.chars().skip(col as usize).take(BOARD_W * cell_w).collect())
+            .collect()
+    }
+
+    #[test]
+    fn fresh_game_draws_no_piece_and_ignores_movement() {
+        let mut game = Game::new();
+        for action in [Action::Left, Action::Right, Action::Rotate, Action::SoftDrop, Action::HardDrop] {
+            game.apply_action(action);
+        }
+        assert!(game.current.is_none());
+        assert!(game.board.cells.iter().all(|&c| c == Cell::Empty));
+        let rows = well_rows(&game, &View::default());
+        assert_eq!(rows.len(), BOARD_H);
+        assert!(rows.iter().all(|row| row.chars().all(|ch| ch == ' ')), "{rows:#?}");
+    }
+
+    #[test]
+    fn a_run_keeps_its_color_from_preview_to_stack() {
+        let mut game = Game::new();
+        game.handle_command_event(crate::CommandEvent::Start {
+            id: 1,
+            command: "cargo build --release".to_string(),
+        });
+        game.hard_drop();
+        let buf = render(&game, &View::default());
+        let lines = buffer_lines(&buf, false);
+        let run_color = color_for_run(1);
+
+        let (col, row) = well_origin(&lines, game.config.cell_w);
+        let bottom = row + BOARD_H as u16 - 1;
+        let locked: Vec<_> = (0..BOARD_W as u16)
+            .map(|x| buf.get(col + x * game.config.cell_w as u16, bottom))
+            .filter(|cell| cell.symbol() != " ")
+            .collect();
+        assert!(!locked.is_empty());
+        assert!(locked.iter().all(|cell| cell.fg == run_color));
+
+        let next_row = lines.iter().position(|l| l.contains("NEXT:")).unwrap();
+        let next_col = lines[next_row][..lines[next_row].find("NEXT:").unwrap()].chars().count() + 7;
+        let first = (next_col as u16..TEST_W)
+            .map(|x| buf.get(x, next_row as u16))
+            .find(|cell| cell.symbol() != " ")
+            .unwrap();
+        assert_eq!(game.peek_next(1)[0].run_id, Some(1));
+        assert_eq!(first.fg, run_color);
+    }
+
+    #[test]
+    fn origin_labels_mark_git_blocks_with_g() {
+        let mut game = Game::new();
+        game.handle_command_event(crate::CommandEvent::Start {
+            id: 1,
+            command: "git status".to_string(),
+        });
+        game.hard_drop();
+        game.process_effects();
+        let labelled = View {
+            origins: true,
+            ..View::default()
+        };
+        let bottom = well_rows(&game, &labelled).pop().unwrap();
+        let cells: Vec<char> = bottom.chars().step_by(game.config.cell_w).filter(|&ch| ch != ' ').collect();
+        assert!(!cells.is_empty());
+        assert!(cells.iter().all(|&ch| ch == 'g'), "{bottom}");
+        let plain = well_rows(&game, &View::default()).pop().unwrap();
+        assert!(plain.chars().step_by(game.config.cell_w).any(|ch| ch != ' ' && ch != 'g'), "{plain}");
+    }
+
+    #[test]
+    fn each_narrow_layout_draws_its_own_stats() {
+        let screen = |layout: NarrowLayout| {
+            let game = game_with(|c| c.narrow_layout = layout);
+            buffer_lines(&render_to_buffer(&game, &View::default(), 30, TEST_H), false).join("\n")
+        };
+        let hidden = screen(NarrowLayout::Hide);
+        assert!(!hidden.contains("SCORE") && !hidden.contains("S:0"));
+        let stacked = screen(NarrowLayout::Stack);
+        assert!(stacked.contains("INFO") && stacked.contains("SCORE:") && stacked.contains("BOMBS:"));
+        let inline = screen(NarrowLayout::Inline);
+        assert!(inline.contains("S:0 L:0 B:0") && !inline.contains("INFO"));
+        for text in [hidden, stacked, inline] {
+            assert!(!text.contains("CONTROLS"));
+        }
+        // The boxes come back as soon as the pane reaches `min_pane_width`.
+        let threshold = game_with(|c| c.min_pane_width = 50);
+        let at = |width| buffer_lines(&render_to_buffer(&threshold, &View::default(), width, TEST_H), false).join("\n");
+        assert!(!at(49).contains("CONTROLS") && at(50).contains("CONTROLS"));
+        let wide = buffer_lines(&render(&Game::new(), &View::default()), false).join("\n");
+        assert!(wide.contains("CONTROLS"));
+    }
+
+    #[test]
+    fn the_piece_and_ghost_rise_with_the_stack() {
+        let mut game = Game::new();
+        game.config.garbage_rise_frames = 2;
+        let mut rows = vec![".........."; BOARD_H];
+        rows[BOARD_H - 1] = "#########.";
+        rows[BOARD_H - 2] = "#########.";
+        game.board = Board::from_ascii(&rows.join("\n"), BOARD_W, BOARD_H).unwrap();
+        assert!(game.inject_piece(Shape::O, 4, vec!['o'; 8]));
+        let find = |rows: &[String], ch: char| rows.iter().position(|r| r.contains(ch)).unwrap();
+        let settled = well_rows(&game, &View::default());
+        game.garbage_rise_rows = 1;
+        game.garbage_rise_frames = 2;
+        let rising = well_rows(&game, &View::default());
+        assert_eq!(find(&rising, 'o'), find(&settled, 'o') + 1);
+        assert_eq!(find(&rising, '·'), find(&settled, '·') + 1);
+    }
+
+    #[test]
+    fn wider_cells_fall_back_to_the_narrow_layout_sooner() {
+        let game = game(config_from(&[("STACK_CELL_W", "3"), ("STACK_NARROW_LAYOUT", "stack")]).unwrap());
+        let screen = |width| buffer_lines(&render_to_buffer(&game, &View::default(), width, TEST_H), false).join("\n");
+        assert!(!screen(45).contains("CONTROLS"));
+        assert!(screen(50).contains("CONTROLS"));
+    }
+
+    #[test]
+    fn infected_cells_use_the_configured_glyph_and_shimmer() {
+        let infected = |config: crate::Config, elapsed: Duration| {
+            let mut game = game(crate::Config { infection_glyph: '@', ..config });
+            let mut rows = vec![".........."; BOARD_H];
+            rows[BOARD_H - 1] = "?.........";
+            game.board = Board::from_ascii(&rows.join("\n"), BOARD_W, BOARD_H).unwrap();
+            game.elapsed = elapsed;
+            let buf = render(&game, &View::default());
+            let (col, row) = well_origin(&buffer_lines(&buf, false), game.config.cell_w);
+            let (glyph, fill) = (buf.get(col, row + BOARD_H as u16 - 1), buf.get(col + 1, row + BOARD_H as u16 - 1));
+            (glyph.symbol().to_string(), fill.symbol().to_string(), glyph.fg)
+        };
+        let (glyph, first, color) = infected(crate::Config::default(), Duration::ZERO);
+        assert_eq!((glyph.as_str(), color), ("@", Color::LightGreen));
+        let (_, next, _) = infected(crate::Config::default(), Duration::from_millis(150));
+        assert_ne!(first, next);
+        let mono = config_with(|c| c.infection_color = None);
+        let (glyph, fill, _) = infected(mono, Duration::ZERO);
+        assert_eq!((glyph.as_str(), fill.as_str()), ("@", "×"));
+    }
+
+    #[test]
+    fn reduced_motion_clears_lines_without_flashing() {
+        for calm in [false, true] {
+            let mut game = game_with(|c| c.reduced_motion = calm);
+            game.board = Board::from_ascii("....######", BOARD_W, BOARD_H).unwrap();
+            assert!(game.inject_piece(Shape::I, 0, vec!['i'; 8]));
+            game.hard_drop();
+            let (mut flashed, mut marked) = (false, false);
+            while !game.pending_clear.is_empty() {
+                flashed |= well_rows(&game, &View::default()).iter().any(|row| row.contains(['█', '▓']));
+                marked |= buffer_lines(&render(&game, &View::default()), false).iter().any(|l| l.contains('▶'));
+                game.process_effects();
+            }
+            assert_eq!(game.lines_cleared, 1);
+            assert_eq!((flashed, marked), (!calm, calm), "reduced motion {calm}");
+        }
+    }
 }
@@ -0,0 +1,88 @@
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+use ratatui::Terminal;
+
+use crate::ui::{draw_game, View};
+use crate::Game;
+
+// Draw the full game screen into an off-screen buffer.
+pub fn render_to_buffer(game: &Game, view: &View, width: u16, height: u16) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).expect("test backend never fails");
+    let _ = terminal.draw(|frame| draw_game(frame, game, view));
+    terminal.backend().buffer().clone()
+}
+
+// Rows of the buffer as text, with SGR escapes for colors when `color` is set.
+pub fn buffer_lines(buf: &Buffer, color: bool) -> Vec<String> {
+    let area = buf.area;
+    (area.top()..area.bottom())
+        .map(|y| {
+            let mut line = String::new();
+            let mut last = None;
+            for x in area.left()..area.right() {
+                let cell = buf.get(x, y);
+                if color {
+                    let style = (cell.fg, cell.bg, cell.modifier);
+                    if last != Some(style) {
+                        line.push_str(&sgr(cell.fg, cell.bg, cell.modifier));
+                        last = Some(style);
+                    }
+                }
+                line.push_str(cell.symbol());
+            }
+            if color {
+                line.push_str("\x1b[0m");
+            }
+            line
+        })
+        .collect()
+}
+
+fn sgr(fg: Color, bg: Color, modifier: Modifier) -> String {
+    let mut codes = vec!["0".to_string()];
+    for (flag, code) in [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+    ] {
+        if modifier.contains(flag) {
+            codes.push(code.to_string());
+        }
+    }
+    if let Some(code) = color_code(fg, false) {
+        codes.push(code);
+    }
+    if let Some(code) = color_code(bg, true) {
+        codes.push(code);
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+fn color_code(color: Color, background: bool) -> Option<String> {
+    let base = if background { 10 } else { 0 };
+    let named = |n: u8| Some((n + base).to_string());
+    match color {
+        Color::Reset => None,
+        Color::Black => named(30),
+        Color::Red => named(31),
+        Color::Green => named(32),
+        Color::Yellow => named(33),
+        Color::Blue => named(34),
+        Color::Magenta => named(35),
+        Color::Cyan => named(36),
+        Color::Gray => named(37),
+        Color::DarkGray => named(90),
+        Color::LightRed => named(91),
+        Color::LightGreen => named(92),
+        Color::LightYellow => named(93),
+        Color::LightBlue => named(94),
+        Color::LightMagenta => named(95),
+        Color::LightCyan => named(96),
+        Color::White => named(97),
+        Color::Rgb(r, g, b) => Some(format!("{};2;{r};{g};{b}", 38 + base)),
+        Color::Indexed(i) => Some(format!("{};5;{i}", 38 + base)),
+    }
+}